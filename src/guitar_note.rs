@@ -1,34 +1,94 @@
 mod chord;
+mod chordpro;
+mod config;
+mod diagram;
+mod fretboard;
+mod key;
+mod midi;
 mod note;
+mod pitch;
+mod roman;
 mod scale;
+mod spelling;
+mod tab;
+mod transpose;
 mod tuning;
+mod voicing;
 
 pub mod guitar_note {
     use super::chord::Chord;
+    use super::chordpro::Song;
+    use super::config::Config;
+    use super::diagram::ChordBox;
+    pub use super::fretboard::FretWindow;
+    use super::key::ChordFunction;
+    use super::key::KeyMatch;
+    use super::midi::MidiFile;
+    pub use super::midi::MidiOptions;
     use super::note::Note;
+    use super::pitch::Pitch;
+    use super::roman::RomanNumeral;
+    pub use super::scale::Harmony;
     use super::scale::Scale;
     use super::scale::ScaleType;
+    use super::spelling::Spelling;
+    use super::tab::Tab;
+    use super::tab::TabEvent;
+    use super::transpose::Transposition;
     use super::tuning::Tuning;
+    use super::voicing::Voicing;
+    pub use super::voicing::VoicingOptions;
+    use itertools::Itertools;
+    use std::cmp;
 
-    fn parse_tab_notation(tab_note: &Vec<String>, tuning: &Tuning) -> Vec<Note> {
+    fn parse_tab_frets(tab_note: &[String], tuning: &Tuning) -> Vec<Option<i32>> {
+        /* One fret per string of the tuning, None for muted (e.g. Ex)
+         * or missing strings.
+         */
         let (note_strs, frets): (Vec<_>, Vec<_>) = tab_note
             .iter()
-            .map(|s| split_string_fret(s))
-            .filter(|x| x.is_some())
-            .map(|x| x.unwrap())
+            .filter_map(|s| match split_string_fret(s) {
+                Some((note_str, fret)) => Some((note_str, Some(fret))),
+                None => split_string_mute(s).map(|note_str| (note_str, None)),
+            })
             .unzip();
-        let offsets = frets
-            .iter()
-            .map(|s| Note { semitones: *s })
-            .collect::<Vec<_>>();
+        let mut result = vec![None; tuning.get_basenotes().len()];
+        for (opt_idx, fret) in tuning.string_indices(&note_strs).iter().zip(frets) {
+            if let Some(idx) = opt_idx {
+                result[*idx] = fret;
+            }
+        }
+        result
+    }
+    fn parse_tab_notation(tab_note: &[String], tuning: &Tuning) -> Vec<Note> {
+        let frets = parse_tab_frets(tab_note, tuning);
         let notes = tuning
-            .tune(&note_strs, &offsets)
+            .tune_frets(&frets)
             .iter()
-            .filter(|x| x.is_some())
-            .map(|x| x.unwrap())
+            .filter_map(|x| *x)
             .collect::<Vec<_>>();
         return notes;
     }
+    fn parse_tab_phrase(tab_note: &[String], tuning: &Tuning) -> Vec<Note> {
+        /* Unlike a chord, a phrase may have several notes per string.
+         * Each note is put on the lowest string of its name.
+         */
+        tab_note
+            .iter()
+            .filter_map(|s| split_string_fret(s))
+            .filter_map(|(note_str, fret)| {
+                let idx = tuning.string_indices(&vec![note_str])[0]?;
+                tuning.fret_pitch(idx, fret)
+            })
+            .collect()
+    }
+    fn split_string_mute(tab_note: &str) -> Option<&str> {
+        let note_str = tab_note.strip_suffix('x')?;
+        Note::NAMES
+            .iter()
+            .find(|n| **n == note_str)
+            .map(|_| note_str)
+    }
     fn split_string_fret<'a>(tab_note: &'a str) -> Option<(&'a str, i32)> {
         // take the longest match in order to prefer match a# over a
         let matched_note_str = Note::NAMES
@@ -72,168 +132,1043 @@ pub mod guitar_note {
     }
 
     pub fn from_tab_notation(note_str: &Vec<String>, tuning: &Tuning) -> Option<String> {
-        /* tab_note string needs to be of format
-         * <string><fret>, e.g. E3 or A10.
+        /* tab_note string needs to be of format <string><fret>, e.g. E3
+         * or A10. Every string of a course sounds, e.g. the octave
+         * strings of a twelve string guitar.
+         */
+        let frets = parse_tab_frets(note_str, tuning);
+        let opt_notes = tuning.tune_courses(&frets);
+        /* spelled as in the chord they make, e.g. Eb3 in an Eb chord,
+         * but open strings keep the name of the tuning, e.g. E2 rather
+         * than Fb2
          */
-        let opt_notes = parse_tab_notation(note_str, tuning);
+        let spelling = match Chord::find_slash_chords(&opt_notes).first() {
+            Some(chord) => chord.spelling(&chord.root_spelling()),
+            None => vec![],
+        };
+        let open_notes = tuning.tune_courses(
+            &frets
+                .iter()
+                .map(|f| f.filter(|f| *f == 0))
+                .collect::<Vec<_>>(),
+        );
         return match opt_notes.len() > 0 {
             true => Some(
                 opt_notes
                     .iter()
-                    .map(|s| s.to_string())
+                    .map(|s| match open_notes.contains(s) {
+                        true => Pitch::from_note(*s).to_string(),
+                        false => Pitch::from_note(*s).spelled(&spelling),
+                    })
                     .collect::<Vec<_>>()
-                    .join(" ")
-                    .to_uppercase(),
+                    .join(" "),
             ),
             false => None,
         };
     }
 
+    pub fn pitch_info(pitch_str: &[String], reference_a4: f64) -> Vec<String> {
+        /* Accepts scientific pitch names (E2, A4), MIDI key numbers or
+         * frequencies (261.6Hz, the closest pitch) and returns name, MIDI
+         * number and frequency for each. Pitches that can't be read get
+         * a line saying so, the others are still shown.
+         */
+        pitch_str
+            .iter()
+            .map(|s| {
+                let opt_pitch = match (s.parse::<i32>(), s.to_lowercase().strip_suffix("hz")) {
+                    (Ok(midi), _) => Pitch::from_midi(midi),
+                    (_, Some(hz)) => hz
+                        .trim()
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|f| Pitch::from_frequency(f, reference_a4)),
+                    _ => Pitch::from_string(s),
+                };
+                /* names keep the spelling they are written with */
+                let spelling = Spelling::split_name(s)
+                    .map(|(spelling, _)| vec![spelling])
+                    .unwrap_or_default();
+                match opt_pitch {
+                    Some(p) => format!(
+                        "{}\tMIDI {}\t{:.2} Hz",
+                        p.spelled(&spelling),
+                        p.midi,
+                        p.frequency(reference_a4)
+                    ),
+                    None => format!(
+                        "{}\tSorry, I could not parse this. Pitches look like E2, A4, C#-1, 0 to 127 or 440Hz.",
+                        s
+                    ),
+                }
+            })
+            .collect()
+    }
+
     pub fn parse_tuning(tuning_name: &str) -> Option<Tuning> {
-        return Tuning::from_name(tuning_name);
+        /* A preset name, a tuning named in the [tunings] section of the
+         * config file or a list of string pitches like "C2 G2 C3 F3 C4 E4".
+         */
+        if let Some(tuning) = Tuning::from_name(tuning_name) {
+            return Some(tuning);
+        }
+        if let Some(spec) = Config::load().get("tunings", tuning_name) {
+            return Tuning::from_string(spec);
+        }
+        Tuning::from_string(tuning_name)
     }
 
     pub fn chord_from_tab_notation(
         note_str: &Vec<String>,
         tuning: &Tuning,
         relative: bool,
-    ) -> (Vec<Option<String>>, String) {
+        window: &FretWindow,
+        rootless: bool,
+    ) -> (Vec<String>, String) {
+        /* The names of the chord over its lowest sounding note, best
+         * first, e.g. Am7 and C6/A. With rootless, shell and rootless
+         * voicings of chords whose root isn't played follow.
+         */
         let notes = parse_tab_notation(note_str, tuning);
-        let chords = Chord::find_chord(&notes);
-        let inversions = chords
-            .iter()
-            .map(|opt_c| {
-                if let Some(c) = opt_c {
-                    Some(c.to_string())
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<Option<String>>>();
+        let mut chords = Chord::find_slash_chords(&notes);
+        if rootless {
+            chords.extend(
+                Chord::find_chord(&notes, true)
+                    .into_iter()
+                    .flatten()
+                    .filter(|c| c.get_rootless().is_some()),
+            );
+        }
+        let names = chords.iter().map(|c| c.to_string()).collect::<Vec<_>>();
 
-        let first_chord_match = chords.into_iter().find_map(|c| c);
-        let fretboard = match first_chord_match {
+        let fretboard = match chords.first() {
             Some(chord) => join_strings(&mut layout_on_fretboard(
                 &notes,
                 &tuning,
                 chord.get_notes().first().unwrap(),
                 relative,
+                &chord.spelling(&chord.root_spelling()),
+                window,
             )),
             None => String::from(""),
         };
-        return (inversions, fretboard);
+        (names, fretboard)
+    }
+    pub fn chord_from_symbol(
+        symbol: &str,
+        tuning: &Tuning,
+        relative: bool,
+        window: &FretWindow,
+    ) -> Option<(String, String, String)> {
+        /* Returns the chord tones, their intervals and the chord
+         * laid out on the fretboard.
+         */
+        let chord = Chord::from_symbol(symbol)?;
+        let mut notes = chord.get_notes().clone();
+        if let Some(bass) = chord.get_bass() {
+            notes.push(bass);
+        }
+        // keep the root as written, e.g. A# stays A# rather than Bb
+        let root = match Spelling::split_name(symbol.trim()) {
+            Some((root, _)) => root,
+            None => chord.root_spelling(),
+        };
+        let spelling = chord.spelling(&root);
+        let note_names = spelling
+            .iter()
+            .map(|s| s.capitalized())
+            .unique()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let intervals = chord.interval_names().join(" ");
+        let fretboard = join_strings(&mut layout_on_fretboard(
+            &notes,
+            tuning,
+            chord.get_notes().first().unwrap(),
+            relative,
+            &spelling,
+            window,
+        ));
+        Some((note_names, intervals, fretboard))
+    }
+    pub fn closest_chords(note_str: &[String], tuning: &Tuning, count: usize) -> Vec<String> {
+        /* Names for notes that make no chord, e.g. "Cadd9 +Db", best
+         * first. Those explaining too little are left out.
+         */
+        let notes = parse_tab_notation(note_str, tuning);
+        Chord::candidates(&notes)
+            .iter()
+            .filter(|c| c.score >= 50)
+            .take(count)
+            .map(|c| c.to_string())
+            .collect()
+    }
+    pub fn chord_box_from_tab_notation(
+        note_str: &[String],
+        tuning: &Tuning,
+        unicode: bool,
+    ) -> Option<String> {
+        /* Renders a vertical chord box, strings not given are muted.
+         */
+        let frets = parse_tab_frets(note_str, tuning);
+        if frets.iter().all(|f| f.is_none()) {
+            return None;
+        }
+        let chord_box = ChordBox::new(frets, tuning.get_string_names().clone());
+        Some(chord_box.render(unicode))
+    }
+    pub fn tab_chord_chart(tab_text: &str, tuning: &Tuning) -> Option<(Vec<String>, String)> {
+        /* Reads an ASCII tab. Returns a line per event with its bar,
+         * its frets in tab notation and its chord, e.g.
+         * "2\ta3 d2 gh2 b1\tC", and a chord chart like "| C | Am G |".
+         */
+        let tab = Tab::parse(tab_text, tuning.get_basenotes().len())?;
+        let names = tuning.get_string_names();
+        let lines = tab
+            .events
+            .iter()
+            .map(|e| {
+                let frets = e
+                    .notes
+                    .iter()
+                    .zip(names)
+                    .filter_map(|(n, name)| {
+                        let n = (*n)?;
+                        let technique = n.technique.map(|t| t.symbol().to_string());
+                        let fret = match n.fret {
+                            Some(fret) => fret.to_string(),
+                            None => String::from("x"),
+                        };
+                        Some(format!("{}{}{}", name, technique.unwrap_or_default(), fret))
+                    })
+                    .collect::<Vec<_>>();
+                let chord = e.chord(tuning).map(|c| c.to_string()).unwrap_or_default();
+                format!("{}\t{}\t{}", e.bar, frets.join(" "), chord)
+            })
+            .collect::<Vec<_>>();
+        let chart = tab
+            .chord_chart(tuning)
+            .iter()
+            .map(|bar| {
+                let chords = bar.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+                format!("| {} ", chords.join(" "))
+            })
+            .collect::<String>();
+        Some((lines, format!("{}|", chart)))
+    }
+    fn parse_chord(chord_str: &[String]) -> Option<Chord> {
+        /* A single argument is read as chord symbol, several as the
+         * note names of the chord, the first one being the root.
+         */
+        if chord_str.len() == 1 {
+            return Chord::from_symbol(&chord_str[0]);
+        }
+        let notes = chord_str
+            .iter()
+            .map(|s| Note::from_string(&s.to_lowercase()))
+            .collect::<Option<Vec<_>>>()?;
+        let root = notes[0].pitch_class();
+        Chord::find_chord(&notes, false)
+            .into_iter()
+            .flatten()
+            .find(|c| c.get_notes()[0].pitch_class() == root)
+    }
+    pub fn chord_voicings(
+        chord_str: &[String],
+        tuning: &Tuning,
+        options: &VoicingOptions,
+    ) -> Option<(String, Vec<String>)> {
+        /* Returns the chord name and its voicings in tab notation,
+         * easiest to play first.
+         */
+        let chord = parse_chord(chord_str)?;
+        let voicings = Voicing::find_voicings(&chord, tuning, options)
+            .iter()
+            .map(|v| v.to_tab_notation(tuning))
+            .collect::<Vec<_>>();
+        Some((chord.to_string(), voicings))
+    }
+    fn sounding_pitches(frets: &[Option<i32>], tuning: &Tuning) -> Vec<Pitch> {
+        /* the pitches of the fretted strings, lowest string first */
+        tuning
+            .tune_frets(frets)
+            .iter()
+            .filter_map(|n| n.map(Pitch::from_note))
+            .collect()
+    }
+    pub fn tab_midi(
+        note_str: &[String],
+        tuning: &Tuning,
+        options: &MidiOptions,
+    ) -> Option<Vec<u8>> {
+        /* the chord in tab notation strummed for a bar */
+        let pitches = sounding_pitches(&parse_tab_frets(note_str, tuning), tuning);
+        if pitches.is_empty() {
+            return None;
+        }
+        let mut midi = MidiFile::new(options);
+        midi.add_strum(&pitches, 4.0);
+        Some(midi.to_bytes())
+    }
+    pub fn progression_midi(
+        chord_str: &Vec<String>,
+        tuning: &Tuning,
+        options: &MidiOptions,
+    ) -> Option<(Vec<u8>, Vec<String>)> {
+        /* Every chord strummed for a bar in its easiest voicing, chords
+         * in tab notation as they are written. Chords that can't be
+         * read or have no voicing in the tuning get an empty bar and
+         * are returned as well. None if no chord could be played.
+         */
+        let mut midi = MidiFile::new(options);
+        let mut skipped = vec![];
+        for s in chord_str {
+            let frets = match s.contains(',') || s.contains(' ') {
+                true => {
+                    let tab = s
+                        .split([',', ' '])
+                        .filter(|t| !t.is_empty())
+                        .map(|t| t.to_lowercase())
+                        .collect::<Vec<_>>();
+                    Some(parse_tab_frets(&tab, tuning))
+                }
+                false => Chord::from_symbol(s).and_then(|chord| {
+                    let options = VoicingOptions::default();
+                    Voicing::find_voicings(&chord, tuning, &options)
+                        .into_iter()
+                        .next()
+                        .map(|voicing| voicing.get_frets().clone())
+                }),
+            };
+            match frets {
+                Some(frets) => midi.add_strum(&sounding_pitches(&frets, tuning), 4.0),
+                None => {
+                    midi.add_strum(&[], 4.0);
+                    skipped.push(s.clone());
+                }
+            }
+        }
+        if skipped.len() == chord_str.len() {
+            return None;
+        }
+        Some((midi.to_bytes(), skipped))
+    }
+    pub fn tab_file_midi(
+        tab_text: &str,
+        tuning: &Tuning,
+        options: &MidiOptions,
+    ) -> Option<Vec<u8>> {
+        /* every event of the tab an eighth note, chords strummed */
+        let tab = Tab::parse(tab_text, tuning.get_basenotes().len())?;
+        let mut midi = MidiFile::new(options);
+        for event in &tab.events {
+            midi.add_strum(&sounding_pitches(&event.frets(), tuning), 0.5);
+        }
+        Some(midi.to_bytes())
+    }
+    pub fn midi_chords(bytes: &[u8]) -> Option<(Vec<String>, String, Option<String>)> {
+        /* Reads a MIDI file. Returns a line per chord event with its
+         * bar and beat, its pitches and its name, e.g.
+         * "3.1\tA2 E3 A3 C4 E4\tAm", a chord chart and the key.
+         */
+        let midi = MidiFile::parse(bytes)?;
+        let events = midi
+            .chord_events()
+            .into_iter()
+            .map(|(start, pitches)| {
+                let notes = pitches.iter().map(|p| p.to_note()).collect::<Vec<_>>();
+                let chord = Chord::find_chord(&notes, false)
+                    .into_iter()
+                    .find_map(|c| c)
+                    .map(|c| c.with_bass(notes[0]));
+                (MidiFile::bar_and_beat(start), pitches, chord)
+            })
+            .collect::<Vec<_>>();
+        let lines = events
+            .iter()
+            .map(|((bar, beat), pitches, chord)| {
+                format!(
+                    "{}.{}\t{}\t{}",
+                    bar,
+                    beat,
+                    pitches.iter().map(|p| p.to_string()).join(" "),
+                    chord.as_ref().map(|c| c.to_string()).unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>();
+        let chart = events
+            .iter()
+            .filter_map(|((bar, _), _, chord)| Some((*bar, chord.as_ref()?.to_string())))
+            .group_by(|(bar, _)| *bar)
+            .into_iter()
+            .map(|(_, bar)| format!("| {} ", bar.map(|(_, name)| name).dedup().join(" ")))
+            .collect::<String>();
+        let chords = events
+            .into_iter()
+            .filter_map(|(_, _, chord)| chord)
+            .collect::<Vec<_>>();
+        let key = KeyMatch::detect(&chords)
+            .into_iter()
+            .next()
+            .map(|k| format!("{} ({:.0}%)", k.name(), k.confidence * 100.0));
+        Some((lines, format!("{}|", chart), key))
+    }
+    pub fn midi_melody_tab(bytes: &[u8], tuning: &Tuning, window: &FretWindow) -> Option<String> {
+        /* the highest voice as tab with the least movement of the hand */
+        let midi = MidiFile::parse(bytes)?;
+        let notes = midi
+            .melody()
+            .iter()
+            .map(|(start, pitch)| (MidiFile::bar_and_beat(*start).0 as usize, pitch.to_note()))
+            .collect::<Vec<_>>();
+        let tab = Tab::fingering(&notes, tuning, window);
+        if tab.events.is_empty() {
+            return None;
+        }
+        Some(tab.render(tuning.get_string_names(), 4))
+    }
+    pub fn with_capo(tuning: Tuning, capo: i32) -> Tuning {
+        tuning.with_capo(capo)
+    }
+    pub fn capo_for_open_shapes(chord_str: &[String], tuning: &Tuning) -> Option<Vec<String>> {
+        /* One line per capo position, best first, e.g.
+         * "Capo 3: G D Em C (4 of 4 in open shapes)". Shapes that
+         * have no open voicing are put in parentheses.
+         */
+        let chords = chord_str
+            .iter()
+            .map(|s| Chord::from_symbol(s))
+            .collect::<Option<Vec<_>>>()?;
+        if chords.is_empty() {
+            return None;
+        }
+        let lines = Voicing::capo_positions(&chords, tuning)
+            .iter()
+            .map(|(capo, shapes)| {
+                let names = shapes
+                    .iter()
+                    .map(|(shape, voicing)| match voicing {
+                        Some(_) => shape.to_string(),
+                        None => format!("({})", shape),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let n_open = shapes.iter().filter(|(_, v)| v.is_some()).count();
+                let position = match capo {
+                    0 => String::from("No capo"),
+                    _ => format!("Capo {}", capo),
+                };
+                format!(
+                    "{}: {} ({} of {} in open shapes)",
+                    position,
+                    names,
+                    n_open,
+                    shapes.len()
+                )
+            })
+            .collect::<Vec<_>>();
+        Some(lines)
+    }
+    fn parse_scale(scale_name: &str, root: Note) -> Option<Scale> {
+        /* A built-in scale or one from the [scales] section of the
+         * config file, e.g. spanish = 1 b2 3 4 5 b6 b7.
+         */
+        if let Ok(scale_type) = scale_name.parse::<ScaleType>() {
+            return Some(Scale::from_type_and_root(root, scale_type));
+        }
+        let config = Config::load();
+        let formula = config.get("scales", scale_name)?;
+        Scale::from_formula(root, formula)
+    }
+    fn scale_layout(
+        scale: &Scale,
+        tuning: &Tuning,
+        relative: bool,
+        window: &FretWindow,
+        harmony: &Harmony,
+    ) -> (String, String, String) {
+        let mut strings = layout_on_fretboard(
+            scale.get_notes(),
+            tuning,
+            scale.get_notes().first().unwrap(),
+            relative,
+            &scale.spelling(),
+            window,
+        );
+        let fretboard = join_strings(&mut strings);
+
+        let degrees = scale.degrees_in_scale().collect::<Vec<_>>();
+        let chords = scale.harmonize(harmony);
+        let mut rows = align_columns(&[degrees, chords]);
+        let chords = rows.pop().unwrap();
+        let degrees = rows.pop().unwrap();
+        (fretboard, degrees, chords)
+    }
+    fn align_columns(rows: &[Vec<String>]) -> Vec<String> {
+        /* Joins the cells of each row with tabs. A cell reaching past
+         * the next tab stop gets the other rows an extra tab, so that
+         * the columns stay aligned.
+         */
+        const TAB: usize = 8;
+        let n_columns = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+        let widths = (0..n_columns)
+            .map(|col| {
+                rows.iter()
+                    .filter_map(|r| r.get(col))
+                    .map(|cell| cell.chars().count() / TAB)
+                    .max()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        rows.iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .map(|(col, cell)| match col + 1 == row.len() {
+                        true => cell.clone(),
+                        false => {
+                            let tabs = widths[col] - cell.chars().count() / TAB + 1;
+                            format!("{}{}", cell, "\t".repeat(tabs))
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect()
     }
     pub fn scale_on_fretboard(
         scale_name: &str,
         root: &str,
         tuning: &Tuning,
         relative: bool,
+        window: &FretWindow,
+        harmony: &Harmony,
     ) -> Option<(String, String, String)> {
         // Interface
-        let opt_root = Note::from_string(root);
-        let parse_result = scale_name.parse::<ScaleType>();
-        match (opt_root, parse_result) {
-            (Some(root), Ok(scale_type)) => {
-                let scale = Scale::from_type_and_root(root, scale_type);
-                let mut strings = layout_on_fretboard(
-                    scale.get_notes(),
-                    &tuning,
-                    scale.get_notes().first().unwrap(),
-                    relative,
-                );
-                let fretboard = join_strings(&mut strings);
-
-                let degrees = scale.degrees_in_scale().collect::<Vec<_>>().join("\t");
-                let chords = scale.chords_in_scale().join("\t");
-                let notes = scale.notes_in_scale().collect::<Vec<_>>().join("\t");
-                return match scale.scale_type {
-                    ScaleType::major_blues
-                    | ScaleType::minor_blues
-                    | ScaleType::major_pentatonic
-                    | ScaleType::minor_pentatonic => Some((fretboard, degrees, notes)),
-                    _ => Some((fretboard, degrees, chords)),
+        let root = Note::from_string(root)?;
+        let scale = parse_scale(scale_name, root)?;
+        Some(scale_layout(&scale, tuning, relative, window, harmony))
+    }
+    fn exercise_tab(notes: &[Note], tuning: &Tuning, window: &FretWindow) -> String {
+        /* up and down in the first position of the window, eighth
+         * notes in four bars per line
+         */
+        let tab = Tab::exercise(notes, tuning, &window.position(), 8);
+        tab.render(tuning.get_string_names(), 4)
+    }
+    pub fn scale_tab(
+        scale_name: &str,
+        root: &str,
+        tuning: &Tuning,
+        window: &FretWindow,
+    ) -> Option<String> {
+        let root = Note::from_string(root)?;
+        let scale = parse_scale(scale_name, root)?;
+        Some(exercise_tab(scale.get_notes(), tuning, window))
+    }
+    pub fn scale_midi(
+        scale_name: &str,
+        root: &str,
+        tuning: &Tuning,
+        window: &FretWindow,
+        options: &MidiOptions,
+    ) -> Option<Vec<u8>> {
+        /* the exercise of --tab in eighth notes */
+        let root = Note::from_string(root)?;
+        let scale = parse_scale(scale_name, root)?;
+        let tab = Tab::exercise(scale.get_notes(), tuning, &window.position(), 8);
+        let pitches = tab
+            .events
+            .iter()
+            .flat_map(|e| sounding_pitches(&e.frets(), tuning))
+            .collect::<Vec<_>>();
+        let mut midi = MidiFile::new(options);
+        midi.add_run(&pitches, 0.5);
+        Some(midi.to_bytes())
+    }
+    pub fn scale_formula_tab(
+        formula: &str,
+        root: &str,
+        tuning: &Tuning,
+        window: &FretWindow,
+    ) -> Option<String> {
+        let root = Note::from_string(root)?;
+        let scale = Scale::from_formula(root, formula)?;
+        Some(exercise_tab(scale.get_notes(), tuning, window))
+    }
+    pub fn chord_tab(symbol: &str, tuning: &Tuning, window: &FretWindow) -> Option<String> {
+        /* the chord as an arpeggio */
+        let chord = Chord::from_symbol(symbol)?;
+        let mut notes = chord.get_notes().clone();
+        notes.extend(chord.get_bass());
+        Some(exercise_tab(&notes, tuning, window))
+    }
+    pub fn voicings_tab(
+        chord_str: &[String],
+        tuning: &Tuning,
+        options: &VoicingOptions,
+        count: usize,
+    ) -> Option<String> {
+        /* The voicings as tab, one per bar, easiest first. */
+        let chord = parse_chord(chord_str)?;
+        let events = Voicing::find_voicings(&chord, tuning, options)
+            .iter()
+            .take(count)
+            .enumerate()
+            .map(|(idx, v)| TabEvent::new(idx + 1, v.get_frets()))
+            .collect();
+        let tab = Tab { events };
+        Some(tab.render(tuning.get_string_names(), 5))
+    }
+    pub fn scale_formula_on_fretboard(
+        formula: &str,
+        root: &str,
+        tuning: &Tuning,
+        relative: bool,
+        window: &FretWindow,
+        harmony: &Harmony,
+    ) -> Option<(String, String, String)> {
+        /* formula lists degrees ("1 2 b3 4 5 b6 7") or steps
+         * ("W H W W H A H").
+         */
+        let root = Note::from_string(root)?;
+        let scale = Scale::from_formula(root, formula)?;
+        Some(scale_layout(&scale, tuning, relative, window, harmony))
+    }
+    pub fn identify_scale(note_str: &[String], tuning: &Tuning) -> Option<Vec<String>> {
+        /* Notes are given in tab notation (e0 a2) or as note names
+         * (a c e). Returns one line per matching scale, best first.
+         */
+        let notes = match note_str
+            .iter()
+            .any(|s| s.chars().any(|c| c.is_ascii_digit()))
+        {
+            true => parse_tab_phrase(note_str, tuning),
+            false => note_str
+                .iter()
+                .map(|s| Spelling::from_string(s).map(|spelling| spelling.pitch_class()))
+                .collect::<Option<Vec<_>>>()?,
+        };
+        if notes.is_empty() {
+            return None;
+        }
+        let n_notes = notes.iter().map(|n| n.pitch_class()).unique().count();
+        let lines = Scale::identify(&notes)
+            .iter()
+            .map(|m| {
+                let unused = match m.unused.is_empty() {
+                    true => String::from("-"),
+                    false => m.unused.iter().map(|s| s.capitalized()).join(" "),
                 };
-            }
-            _ => {
-                return None;
-            }
+                format!(
+                    "{} {:?}\t{} of {} notes\tunused: {}",
+                    m.scale.spelling()[0].capitalized(),
+                    m.scale.scale_type,
+                    m.matched,
+                    n_notes,
+                    unused
+                )
+            })
+            .collect::<Vec<_>>();
+        Some(lines)
+    }
+    fn parse_progression(chord_str: &[String], tuning: &Tuning) -> Option<Vec<Chord>> {
+        /* Each argument is a chord symbol (Am7) or a chord in tab
+         * notation with the strings separated by commas or spaces
+         * (a0,d2,g2,b1,e0).
+         */
+        chord_str
+            .iter()
+            .map(|s| match s.contains(',') || s.contains(' ') {
+                true => {
+                    let tab = s
+                        .split([',', ' '])
+                        .filter(|t| !t.is_empty())
+                        .map(|t| t.to_lowercase())
+                        .collect::<Vec<_>>();
+                    let notes = parse_tab_notation(&tab, tuning);
+                    Chord::find_slash_chords(&notes).into_iter().next()
+                }
+                false => Chord::from_symbol(s),
+            })
+            .collect()
+    }
+    pub fn detect_key(chord_str: &[String], tuning: &Tuning) -> Option<Vec<String>> {
+        /* One line per key, most likely first, e.g.
+         * "C major\t83%\tE7: secondary dominant of Am".
+         */
+        let chords = parse_progression(chord_str, tuning)?;
+        if chords.is_empty() {
+            return None;
         }
+        let lines = KeyMatch::detect(&chords)
+            .iter()
+            .map(|key| {
+                let flagged = chords
+                    .iter()
+                    .zip(key.functions.iter())
+                    .filter(|(_, f)| !matches!(f, ChordFunction::Diatonic))
+                    .map(|(c, f)| format!("{}: {}", c, f))
+                    .unique()
+                    .collect::<Vec<_>>();
+                let flagged = match flagged.is_empty() {
+                    true => String::from("all diatonic"),
+                    false => flagged.join(", "),
+                };
+                format!(
+                    "{}\t{:.0}%\t{}",
+                    key.name(),
+                    key.confidence * 100.0,
+                    flagged
+                )
+            })
+            .collect::<Vec<_>>();
+        Some(lines)
     }
-    fn pad_to_length(cifar: &String) -> String {
-        if cifar.len() < 2 {
-            return format!("{} ", cifar);
-        } else {
-            return format!("{}", cifar);
+    fn parse_key(key_str: &str) -> Option<Scale> {
+        /* A key such as C, Am, "Bb minor" or "D dorian".
+         */
+        let (root, rest) = Spelling::split_name(key_str.trim())?;
+        let scale_type = match rest.trim() {
+            "" => ScaleType::major,
+            "m" => ScaleType::minor,
+            name => name.to_lowercase().parse::<ScaleType>().ok()?,
+        };
+        let scale = Scale::from_type_and_root(root.pitch_class(), scale_type);
+        match scale.is_heptatonic() {
+            true => Some(scale),
+            false => None,
+        }
+    }
+    pub fn roman_numerals(
+        key_str: &str,
+        chord_str: &[String],
+        tuning: &Tuning,
+    ) -> Option<(String, String, String)> {
+        /* Returns the key, the chords and their numerals, the last two
+         * separated by tabs so that they line up.
+         */
+        let scale = parse_key(key_str)?;
+        let chords = parse_progression(chord_str, tuning)?;
+        let numerals = chords
+            .iter()
+            .map(|c| RomanNumeral::analyze(c, &scale).to_string())
+            .join("\t");
+        let names = chords.iter().map(|c| c.to_string()).join("\t");
+        let key = format!(
+            "{} {:?}",
+            scale.spelling()[0].capitalized(),
+            scale.scale_type
+        );
+        Some((key, names, numerals))
+    }
+    pub fn chords_from_roman(key_str: &str, numeral_str: &[String]) -> Option<Vec<String>> {
+        /* One line per numeral with the chord symbol and its notes,
+         * e.g. "ii\tCm\tC Eb G" in Bb major.
+         */
+        let scale = parse_key(key_str)?;
+        numeral_str
+            .iter()
+            .map(|s| {
+                let (root, chord) = RomanNumeral::from_string(s)?.to_chord(&scale)?;
+                /* a bass that is a chord tone is listed once */
+                let notes = chord
+                    .spelling(&root)
+                    .iter()
+                    .map(|n| n.capitalized())
+                    .unique()
+                    .join(" ");
+                Some(format!(
+                    "{}\t{}\t{}",
+                    s,
+                    chord.to_string_with_root(&root),
+                    notes
+                ))
+            })
+            .collect()
+    }
+    fn parse_transposition(key: &Scale, transpose: &str) -> Option<Transposition> {
+        /* Semitones (2, -3) or a key (Bb, Em). A key without a mode
+         * keeps the one of the song, so C is C minor for a song in A minor.
+         */
+        if let Ok(semitones) = transpose.parse::<i32>() {
+            return Some(Transposition::new(key, semitones));
         }
+        let target = match Spelling::split_name(transpose.trim())? {
+            (root, "") => Scale::from_type_and_root(root.pitch_class(), key.scale_type.clone()),
+            _ => parse_key(transpose)?,
+        };
+        Some(Transposition::to_key(key, target))
     }
-    pub fn all_notes_on_fretboard(note_names: &Vec<String>, tuning: &Tuning) -> Option<String> {
-        let notes = note_names
+    pub fn transpose_progression(
+        chord_str: &[String],
+        transpose: &str,
+        tuning: &Tuning,
+    ) -> Option<(String, String)> {
+        /* Returns the new key and the chords named in it, e.g.
+         * "Bb minor" and "Bbm Gb Db Ab" for Am F C G up a semitone.
+         */
+        let chords = parse_progression(chord_str, tuning)?;
+        let key = KeyMatch::detect(&chords).into_iter().next()?.scale;
+        let transposition = parse_transposition(&key, transpose)?;
+        let names = chords.iter().map(|c| transposition.chord_name(c)).join(" ");
+        Some((KeyMatch::scale_name(&transposition.key), names))
+    }
+    pub fn easiest_keys(chord_str: &[String], tuning: &Tuning) -> Option<Vec<String>> {
+        /* One line per transposition, fewest barre chords first, e.g.
+         * "-3 (G major): G D Em C (0 barre chords)". Chords without a
+         * voicing count as barre chords and are put in parentheses.
+         */
+        let chords = parse_progression(chord_str, tuning)?;
+        let key = KeyMatch::detect(&chords).into_iter().next()?.scale;
+        let lines = Voicing::transpositions(&chords, tuning)
             .iter()
-            .filter_map(|note_str| Note::from_string(note_str))
+            .map(|(semitones, voiced)| {
+                let transposition = Transposition::new(&key, *semitones);
+                let names = chords
+                    .iter()
+                    .zip(voiced.iter())
+                    .map(|(c, (_, v))| match v {
+                        Some(_) => transposition.chord_name(c),
+                        None => format!("({})", transposition.chord_name(c)),
+                    })
+                    .join(" ");
+                let barres = voiced
+                    .iter()
+                    .filter(|(_, v)| v.as_ref().is_none_or(|v| v.is_barre()))
+                    .count();
+                format!(
+                    "{:+} ({}): {} ({} barre chords)",
+                    semitones,
+                    KeyMatch::scale_name(&transposition.key),
+                    names,
+                    barres
+                )
+            })
+            .collect::<Vec<_>>();
+        Some(lines)
+    }
+    fn song_key(song: &Song) -> Option<Scale> {
+        /* the key directive or else the most likely key of the chords */
+        if let Some(key) = song.directive("key").and_then(parse_key) {
+            return Some(key);
+        }
+        let chords = song
+            .chords()
+            .iter()
+            .filter_map(|c| c.chord.clone())
+            .collect::<Vec<_>>();
+        KeyMatch::detect(&chords)
+            .into_iter()
+            .next()
+            .map(|k| k.scale)
+    }
+    fn read_song(text: &str, transpose: Option<&str>, tuning: &Tuning) -> Option<Song> {
+        /* Transposes by semitones (2, -3) or to a key (Bb, Em), and to
+         * the shapes to play with the capo of the tuning.
+         */
+        let mut song = Song::parse(text)?;
+        if let Some(transpose) = transpose {
+            let transposition = parse_transposition(&song_key(&song)?, transpose)?;
+            song = song.transposed(&transposition);
+        }
+        if tuning.get_capo() > 0 {
+            song = song.for_capo(tuning.get_capo());
+        }
+        Some(song)
+    }
+    pub fn chordpro_lead_sheet(
+        text: &str,
+        transpose: Option<&str>,
+        tuning: &Tuning,
+        unicode: bool,
+    ) -> Option<String> {
+        /* The song as lyrics with the chords above them, followed by
+         * a chord box of every chord, in open shapes where possible.
+         */
+        let song = read_song(text, transpose, tuning)?;
+        let open_tuning = tuning.clone().with_capo(0);
+        let boxes = song
+            .chords()
+            .into_iter()
+            .unique_by(|c| c.symbol.clone())
+            .filter_map(|c| {
+                let chord = c.chord.as_ref()?;
+                let voicing = Voicing::open_shape(chord, &open_tuning).or_else(|| {
+                    let options = VoicingOptions::default();
+                    Voicing::find_voicings(chord, &open_tuning, &options)
+                        .into_iter()
+                        .next()
+                })?;
+                let frets = voicing.get_frets().clone();
+                let names = open_tuning.get_string_names().clone();
+                Some((c.symbol.clone(), ChordBox::new(frets, names)))
+            })
+            .collect::<Vec<_>>();
+        let mut lines = song.render();
+        for row in &boxes.into_iter().chunks(4) {
+            lines.push(String::new());
+            lines.push(ChordBox::side_by_side(&row.collect::<Vec<_>>(), unicode));
+        }
+        Some(lines.join("\n"))
+    }
+    pub fn chordpro_transposed(
+        text: &str,
+        transpose: Option<&str>,
+        tuning: &Tuning,
+    ) -> Option<String> {
+        Some(read_song(text, transpose, tuning)?.to_chordpro())
+    }
+    pub fn scale_names() -> Vec<String> {
+        let mut names = ScaleType::names();
+        names.extend(
+            Config::load()
+                .entries("scales")
+                .into_iter()
+                .map(|(name, _)| name)
+                .unique(),
+        );
+        names
+    }
+    fn pad_to_length(cifar: &str, width: usize) -> String {
+        format!("{:<width$}", cifar, width = width)
+    }
+    pub fn label_width(fretboard: &str) -> usize {
+        /* The width of the widest note name of a fretboard layout, at
+         * least two so that fret numbers fit.
+         */
+        fretboard
+            .split_whitespace()
+            .map(|cell| cell.trim_end_matches('|').chars().count())
+            .fold(2, cmp::max)
+    }
+    pub fn all_notes_on_fretboard(
+        note_names: &[String],
+        tuning: &Tuning,
+        window: &FretWindow,
+    ) -> Option<String> {
+        let spelling = note_names
+            .iter()
+            .filter_map(|note_str| Spelling::from_string(note_str))
+            .collect::<Vec<_>>();
+        let notes = spelling
+            .iter()
+            .map(|s| s.pitch_class())
             .collect::<Vec<Note>>();
+        if notes.is_empty() {
+            return None;
+        }
         return Some(join_strings(&mut layout_on_fretboard(
-            &notes, &tuning, &notes[0], false,
+            &notes, tuning, &notes[0], false, &spelling, window,
         )));
     }
-    pub fn print_fret_numbers() -> String {
-        let fret_numbers = (1..24)
-            .map(|x| pad_to_length(&x.to_string()))
+    pub fn print_fret_numbers(window: &FretWindow, offset: i32, width: usize) -> String {
+        /* offset is added to the numbers, e.g. to count from the nut
+         * rather than from a capo. width is the label width of the
+         * fretboard the numbers go with.
+         */
+        let fret_numbers = window
+            .fretted()
+            .map(|x| pad_to_length(&(x + offset).to_string(), width))
             .collect::<Vec<_>>()
             .join("  ");
-        return "      ".to_owned() + &fret_numbers;
-    }
-    pub fn print_fret_markers() -> String {
-        let fret_markers = vec![
-            "   ", "  ", "  ", "* ", "  ", "* ", "  ", "* ", "  ", "* ", "  ", "  ", ": ",
-            /*  0    1   2    3     4    5    6    7   8    9   10    11   12 */
-            "  ", "  ", "* ", "  ", "* ", "  ", "* ", "  ", "* ", "  ", "* ",
-            "  ", /*  13  14   15    16     17    18    19    20   21    22   23   24*/
-        ]
-        .join("  ");
-        return " ".to_owned() + &fret_markers;
+        " ".repeat(width + 4) + &fret_numbers
+    }
+    pub fn print_fret_markers(window: &FretWindow, capo: i32, width: usize) -> String {
+        /* Inlays sit on the 3rd, 5th, 7th, 9th and 12th fret of the neck,
+         * the first cell being the one behind the capo.
+         */
+        let fret_markers = window
+            .fretted()
+            .map(|x| match (x + capo) % 12 {
+                3 | 5 | 7 | 9 => "*",
+                0 => ":",
+                _ => "",
+            })
+            .map(|marker| pad_to_length(marker, width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        " ".repeat(width + 4) + &fret_markers
     }
     fn layout_on_fretboard(
         notes: &Vec<Note>,
         tuning: &Tuning,
         root: &Note,
         relative: bool,
+        spelling: &[Spelling],
+        window: &FretWindow,
     ) -> Vec<String> {
+        /* spelling gives the names of the notes, e.g. bb instead of a#;
+         * notes without a spelling fall back to sharps. Only the frets
+         * of the window are laid out, the nut column names the string
+         * when the window starts further up the neck.
+         */
         let mut result: Vec<String> = vec![];
-        let root_str = root.to_string();
-        let capitalize_if_root = |s: &str| {
-            if s == root_str {
-                s.to_uppercase()
-            } else {
-                s.to_owned()
-            }
+        let name_in = |note: Note, spelling: &[Spelling]| match spelling
+            .iter()
+            .find(|s| s.pitch_class() == note.pitch_class())
+        {
+            Some(s) if note.pitch_class() == root.pitch_class() => s.capitalized(),
+            Some(s) => s.to_string(),
+            None if note.pitch_class() == root.pitch_class() => note.to_string().to_uppercase(),
+            None => note.to_string().to_owned(),
         };
         let print_note = |base_note: &Note, note: &Note, relative: bool| {
             if relative {
                 return Scale::note_to_degree(root, &(*base_note + *note)).to_owned();
             } else {
-                return capitalize_if_root((*base_note + *note).to_string());
+                name_in(*base_note + *note, spelling)
             }
         };
-        for base_note in tuning.get_basenotes().iter() {
+        let first_frets = tuning.get_first_frets();
+        let labels = tuning
+            .get_basenotes()
+            .iter()
+            .enumerate()
+            .map(|(idx, base_note)| {
+                locate_on_string(notes, base_note, window)
+                    .into_iter()
+                    .zip(window.frets())
+                    /* short strings start further up the neck */
+                    .map(|(note, fret)| (note.filter(|_| fret >= first_frets[idx]), fret))
+                    .map(|(note, fret)| {
+                        note.map(|n| match fret {
+                            /* open strings keep the name of the tuning,
+                             * e.g. e rather than fb
+                             */
+                            0 if !relative => name_in(*base_note + n, &[]),
+                            _ => print_note(base_note, &n, relative),
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        /* every cell is as wide as the widest name, e.g. bbb */
+        let width = labels
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|label| label.chars().count())
+            .fold(2, cmp::max);
+        let string_names = tuning.get_string_names();
+        for (idx, string_labels) in labels.iter().enumerate() {
             // for each string on guitar
-            let frets = locate_on_string(notes, base_note);
-            let mut nut_string = String::from("");
-
-            if let Some(note) = frets[0] {
-                let note_str = print_note(base_note, &note, relative);
-                nut_string = format!(" {}|", pad_to_length(&note_str));
+            let mut fret_strings = vec![];
+            let mut cells = string_labels.iter();
+            if window.shows_nut() {
+                match cells.next() {
+                    Some(Some(label)) => {
+                        fret_strings.push(format!(" {}|", pad_to_length(label, width)))
+                    }
+                    _ => fret_strings.push(format!("{}|", " ".repeat(width + 1))),
+                }
             } else {
-                nut_string += "   |";
+                /* no nut, the string is named instead */
+                fret_strings.push(format!(" {} ", pad_to_length(&string_names[idx], width)));
             }
-            let mut fret_strings = vec![nut_string];
-
-            for f in frets.iter().skip(1) {
-                if let Some(note) = f {
-                    let mut note_string = print_note(base_note, note, relative);
-                    note_string = pad_to_length(&note_string);
-                    fret_strings.push(note_string);
-                } else {
-                    fret_strings.push(String::from("- "));
+            for label in cells {
+                match label {
+                    Some(label) => fret_strings.push(pad_to_length(label, width)),
+                    None => fret_strings.push(pad_to_length("-", width)),
                 }
             }
             result.push(fret_strings.join("  "));
@@ -241,20 +1176,25 @@ pub mod guitar_note {
         return result;
     }
 
-    fn locate_on_string(notes: &Vec<Note>, base_note: &Note) -> [Option<Note>; 24] {
-        let mut frets: [Option<Note>; 24] = [None; 24];
-        for note in notes.iter() {
-            let mut fret1 = ((note.no_octaves()) - *base_note).no_octaves();
-            if fret1.semitones < 0 {
-                // rust modulo works differently from python modulo
-                fret1 = fret1 + Note::octave();
-            }
-            let fret2 = fret1 + Note::octave();
-
-            frets[fret1.semitones as usize] = Some(fret1);
-            frets[fret2.semitones as usize] = Some(fret2);
-        }
-        return frets;
+    fn locate_on_string(
+        notes: &[Note],
+        base_note: &Note,
+        window: &FretWindow,
+    ) -> Vec<Option<Note>> {
+        /* For each fret of the window the interval above the open
+         * string, if it is one of the notes.
+         */
+        window
+            .frets()
+            .map(|fret| Note { semitones: fret })
+            .map(|interval| {
+                let note = (*base_note + interval).pitch_class();
+                match notes.iter().any(|n| n.pitch_class() == note) {
+                    true => Some(interval),
+                    false => None,
+                }
+            })
+            .collect()
     }
 
     fn join_strings(fret_strings: &mut Vec<String>) -> String {
@@ -274,12 +1214,228 @@ pub mod guitar_note {
         assert_eq!(r2, 5);
     }
 
+    #[test]
+    fn test_chord_from_symbol() {
+        let window = FretWindow::new(FretWindow::DEFAULT_NECK);
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let (notes, intervals, _) = chord_from_symbol("F#m7b5", &tuning, false, &window).unwrap();
+        assert_eq!(notes, "F# A C E");
+        assert_eq!(intervals, "1 3m 5- 7");
+        let (notes, _, _) = chord_from_symbol("C/G", &tuning, false, &window).unwrap();
+        assert_eq!(notes, "C E G");
+        assert_eq!(chord_from_symbol("Xm7", &tuning, false, &window), None);
+    }
+
+    #[test]
+    fn test_chord_voicings() {
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let options = VoicingOptions::default();
+        let notes = ["a", "c", "e", "g"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let (name, voicings) = chord_voicings(&notes, &tuning, &options).unwrap();
+        assert_eq!(name, "Am7");
+        assert!(!voicings.is_empty());
+        let notes = ["c", "e", "g", "a"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(chord_voicings(&notes, &tuning, &options).unwrap().0, "C6");
+    }
+    #[test]
+    fn test_pitch_info() {
+        let pitches = ["E2", "A4", "128", "64", "Bb3", "70", "261Hz"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let lines = pitch_info(&pitches, 440.0);
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[1], "A4\tMIDI 69\t440.00 Hz");
+        assert!(lines[2].starts_with("128\tSorry"));
+        assert_eq!(lines[3], "E4\tMIDI 64\t329.63 Hz");
+        assert!(lines[4].starts_with("Bb3\t"));
+        assert!(lines[5].starts_with("Bb4\t"));
+        assert_eq!(lines[6], "C4\tMIDI 60\t261.63 Hz");
+    }
+
+    #[test]
+    fn test_from_tab_notation() {
+        let window = FretWindow::new(FretWindow::DEFAULT_NECK);
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let tab = ["e0", "a2", "d2", "g1", "b0", "e0"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            from_tab_notation(&tab, &tuning),
+            Some(String::from("E2 B2 E3 G#3 B3 E4"))
+        );
+        /* an E shape behind a capo on the third fret is a G */
+        let capo = with_capo(tuning.clone(), 3);
+        assert_eq!(
+            from_tab_notation(&tab, &capo),
+            Some(String::from("G2 D3 G3 B3 D4 G4"))
+        );
+        let (chords, _) = chord_from_tab_notation(&tab, &capo, false, &window, false);
+        assert_eq!(chords[0], "G");
+        /* spelled in the chord, not with sharps */
+        let tab = ["a6", "d5", "g3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            from_tab_notation(&tab, &tuning),
+            Some(String::from("Eb3 G3 Bb3"))
+        );
+        /* open strings keep their names, even over an Eb chord */
+        let tab = ["e0", "a1", "d1", "g3", "b4", "e3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            from_tab_notation(&tab, &tuning),
+            Some(String::from("E2 Bb2 Eb3 Bb3 Eb4 G4"))
+        );
+        /* a C shape with the third in the bass */
+        let tab = ["a7", "d5", "g5", "b5"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let (chords, _) = chord_from_tab_notation(&tab, &tuning, false, &window, false);
+        assert_eq!(chords[0], "C/E");
+        /* the third and seventh of a G7 */
+        let tab = ["a2", "d3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let (chords, _) = chord_from_tab_notation(&tab, &tuning, false, &window, true);
+        assert!(chords.contains(&String::from("G7 (shell)")));
+    }
+
+    #[test]
+    fn test_tab_chord_chart() {
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let text = "e|-0---0-|-3-----|
+B|-1---1-|-0-----|
+G|-0h2---|-0-----|
+D|-2-----|-0-----|
+A|-3-----|-2-----|
+E|-------|-3-x---|";
+        let (lines, chart) = tab_chord_chart(text, &tuning).unwrap();
+        assert_eq!(lines[0], "1\ta3 d2 g0 b1 e0\tC");
+        assert_eq!(lines[1], "1\tgh2\t");
+        assert_eq!(lines[3], "2\te3 a2 d0 g0 b0 e3\tG");
+        assert_eq!(lines[4], "2\tex\t");
+        assert_eq!(chart, "| C | G |");
+    }
+
+    #[test]
+    fn test_scale_tab() {
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let window = FretWindow::from_string("5-8", 24).unwrap();
+        let tab = scale_tab("minor_pentatonic", "a", &tuning, &window).unwrap();
+        let first_staff = tab.lines().take(6).collect::<Vec<_>>();
+        assert_eq!(
+            first_staff[5],
+            "e|-5-8-------------|-----------------|-----------8-5-|"
+        );
+        assert_eq!(
+            first_staff[0],
+            "e|-----------------|-----5-8-5-------|---------------|"
+        );
+        let tab = chord_tab("C", &tuning, &FretWindow::new(24)).unwrap();
+        assert!(tab.starts_with("e|"));
+    }
+
+    #[test]
+    fn test_transpose_progression() {
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let progression = ["Am", "F", "C", "G/B"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let (key, names) = transpose_progression(&progression, "Bbm", &tuning).unwrap();
+        assert_eq!(key, "Bb minor");
+        assert_eq!(names, "Bbm Gb Db Ab/C");
+        let (_, names) = transpose_progression(&progression, "-2", &tuning).unwrap();
+        assert_eq!(names, "Gm Eb Bb F/A");
+        /* a key without a mode keeps the minor */
+        let (key, names) = transpose_progression(&progression, "C", &tuning).unwrap();
+        assert_eq!(key, "C minor");
+        assert_eq!(names, "Cm Ab Eb Bb/D");
+        let keys = easiest_keys(&progression, &tuning).unwrap();
+        assert_eq!(keys[0], "-5 (E minor): Em C G D/F# (0 barre chords)");
+    }
+
+    #[test]
+    fn test_midi() {
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let options = MidiOptions::default();
+        let window = FretWindow::from_string("5-8", 24).unwrap();
+        let midi = scale_midi("minor_pentatonic", "a", &tuning, &window, &options).unwrap();
+        /* A2 on the fifth fret of the low e string */
+        let first_note_on = midi.windows(2).position(|w| w == [0x90, 45]);
+        assert!(first_note_on.is_some());
+        let capo = tuning.clone().with_capo(2);
+        let tab = vec![String::from("e0")];
+        let midi = tab_midi(&tab, &capo, &options).unwrap();
+        assert!(midi.windows(2).any(|w| w == [0x90, 42]));
+        let progression = ["Am", "F", "C", "e3,a2,d0,g0,b0,e3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let (midi, skipped) = progression_midi(&progression, &tuning, &options).unwrap();
+        assert!(skipped.is_empty());
+        let (lines, chart, key) = midi_chords(&midi).unwrap();
+        assert_eq!(lines[0], "1.1\tA2 E3 A3 C4 E4\tAm");
+        assert_eq!(chart, "| Am | F | C | G |");
+        assert_eq!(key.unwrap(), "A minor (83%)");
+        let melody = midi_melody_tab(&midi, &tuning, &FretWindow::new(24)).unwrap();
+        assert!(melody.starts_with("e|-0-|-1-|-0-|-3-|"));
+        /* a chord without a voicing leaves its bar empty, C13 needs
+         * five strings
+         */
+        let ukulele = Tuning::from_name("ukulele").unwrap();
+        let progression = ["Am", "C13", "Xm", "C"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let (midi, skipped) = progression_midi(&progression, &ukulele, &options).unwrap();
+        assert_eq!(skipped, vec!["C13", "Xm"]);
+        let (lines, _, _) = midi_chords(&midi).unwrap();
+        assert!(lines[1].starts_with("4.1\t"));
+    }
+
+    #[test]
+    fn test_chordpro() {
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let text = "{title: Scarborough Fair}\n[Em]Are you going to [D]Scarborough [Em]Fair";
+        let song = chordpro_transposed(text, Some("Gm"), &tuning).unwrap();
+        assert!(song.ends_with("[Gm]Are you going to [F]Scarborough [Gm]Fair"));
+        let capo = tuning.clone().with_capo(3);
+        let song = chordpro_transposed(text, Some("3"), &capo).unwrap();
+        assert!(song.contains("{capo: 3}\n[Em]Are"));
+        /* a key name without a mode keeps the minor of {key: Em} */
+        let in_e_minor = "{key: Em}\n[Em]Are you going to [D]Scarborough [Em]Fair";
+        let song = chordpro_transposed(in_e_minor, Some("Bb"), &tuning).unwrap();
+        assert_eq!(
+            song,
+            "{key: Bbm}\n[Bbm]Are you going to [Ab]Scarborough [Bbm]Fair"
+        );
+        let sheet = chordpro_lead_sheet(text, None, &tuning, false).unwrap();
+        assert!(sheet.starts_with("SCARBOROUGH FAIR\nEm"));
+        assert!(sheet.contains("\n     Em                    D\n"));
+    }
+
     #[test]
     fn test_scale_print() {
+        let window = FretWindow::new(FretWindow::DEFAULT_NECK);
         let scale_name = "major_blues";
         let root = "a";
         let tuning = Tuning::from_name("eadgbe").unwrap();
-        let opt_result = scale_on_fretboard(scale_name, root, &tuning, false);
+        let opt_result =
+            scale_on_fretboard(scale_name, root, &tuning, false, &window, &Harmony::triads);
         if let Some(result) = opt_result {
             println!("{}", result.2);
             println!("{}", result.1);
@@ -287,7 +1443,8 @@ pub mod guitar_note {
         } else {
             panic!("Something went wrong");
         }
-        let opt_result = scale_on_fretboard(scale_name, root, &tuning, true);
+        let opt_result =
+            scale_on_fretboard(scale_name, root, &tuning, true, &window, &Harmony::triads);
         if let Some(result) = opt_result {
             println!("{}", result.2);
             println!("{}", result.1);
@@ -296,4 +1453,152 @@ pub mod guitar_note {
             panic!("Something went wrong");
         }
     }
+    #[test]
+    fn test_identify_scale() {
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let tab = ["e5", "e8", "a5", "a7", "d5", "d7"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let lines = identify_scale(&tab, &tuning).unwrap();
+        assert_eq!(lines[0], "A minor_pentatonic\t5 of 5 notes\tunused: -");
+        let names = ["g", "a", "b", "c", "d", "e", "f#"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let lines = identify_scale(&names, &tuning).unwrap();
+        assert_eq!(lines[0], "G major\t7 of 7 notes\tunused: -");
+        assert_eq!(identify_scale(&[String::from("h")], &tuning), None);
+    }
+    #[test]
+    fn test_detect_key() {
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let progression = ["C", "a0,d2,g2,b1,e0", "D7", "G", "Fm", "C"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let lines = detect_key(&progression, &tuning).unwrap();
+        assert_eq!(
+            lines[0],
+            "C major\t88%\tD7: secondary dominant of G, Fm: borrowed from C minor"
+        );
+        assert_eq!(detect_key(&[String::from("H7")], &tuning), None);
+    }
+    #[test]
+    fn test_roman_numerals() {
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let progression = ["Dm7", "G7", "Cmaj7", "e0,a3,d2,g0,b1,e0", "D7", "Bb"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let (key, names, numerals) = roman_numerals("c", &progression, &tuning).unwrap();
+        assert_eq!(key, "C major");
+        assert_eq!(names, "Dm7\tG7\tCmaj7\tC/E\tD7\tBb");
+        assert_eq!(numerals, "ii7\tV7\tImaj7\tI6\tV7/V\tbVII");
+        let numerals = ["ii", "V", "I"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let lines = chords_from_roman("Bb", &numerals).unwrap();
+        assert_eq!(
+            lines,
+            vec!["ii\tCm\tC Eb G", "V\tF\tF A C", "I\tBb\tBb D F"]
+        );
+        let numerals = ["I6", "V6/5"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let lines = chords_from_roman("Bb", &numerals).unwrap();
+        assert_eq!(lines, vec!["I6\tBb/D\tBb D F", "V6/5\tF7/A\tF A C Eb"]);
+        assert_eq!(chords_from_roman("Bb", &[String::from("VIII")]), None);
+    }
+    #[test]
+    fn test_layout_on_fretboard() {
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let a = Note { semitones: 9 };
+        let scale = Scale::from_type_and_root(a, ScaleType::major);
+        let notes = scale.get_notes().to_vec();
+        let spelling = scale.spelling();
+        let rows = layout_on_fretboard(
+            &notes,
+            &tuning,
+            &a,
+            false,
+            &spelling,
+            &FretWindow::new(FretWindow::DEFAULT_NECK),
+        );
+        assert!(rows[0].starts_with(" e |  -   f#"));
+        /* the open e strings of an Fb chord are still e */
+        let fb = Note { semitones: 4 };
+        let chord = Chord::from_symbol("Fb").unwrap();
+        let rows = layout_on_fretboard(
+            chord.get_notes(),
+            &tuning,
+            &fb,
+            false,
+            &chord.spelling(&Spelling::from_string("fb").unwrap()),
+            &FretWindow::new(FretWindow::DEFAULT_NECK),
+        );
+        assert!(rows[0].starts_with(" E |"));
+        assert!(rows[0].contains("  Fb"));
+        /* without the nut, each row starts with the name of its
+         * string, lowest string first
+         */
+        let window = FretWindow::from_string("5-9", 24).unwrap();
+        let rows = layout_on_fretboard(&notes, &tuning, &a, false, &spelling, &window);
+        let names = rows
+            .iter()
+            .map(|row| row[..4].to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![" e  ", " a  ", " d  ", " g  ", " b  ", " e  "]);
+    }
+    #[test]
+    fn test_fret_window() {
+        let tuning = Tuning::from_name("eadgbe").unwrap();
+        let window = FretWindow::from_string("5-9", 24).unwrap();
+        assert_eq!(
+            print_fret_numbers(&window, 0, 2),
+            "      5   6   7   8   9 "
+        );
+        assert_eq!(
+            print_fret_markers(&window, 0, 2),
+            "      *       *       * "
+        );
+        let (fretboard, _, _) =
+            scale_on_fretboard("major", "a", &tuning, false, &window, &Harmony::triads).unwrap();
+        let low_e = fretboard.lines().last().unwrap().to_owned();
+        assert_eq!(low_e, " e    A   -   b   -   c#");
+
+        let classical = FretWindow::new(19);
+        let (fretboard, _, _) =
+            scale_on_fretboard("major", "a", &tuning, false, &classical, &Harmony::triads).unwrap();
+        assert_eq!(
+            fretboard.lines().last().unwrap().len(),
+            print_fret_numbers(&classical, 0, 2).trim_end().len()
+        );
+    }
+    #[test]
+    fn test_other_instruments() {
+        let window = FretWindow::new(FretWindow::DEFAULT_NECK);
+        let bass = Tuning::from_name("bass").unwrap();
+        let (fretboard, _, _) =
+            scale_on_fretboard("major", "e", &bass, false, &window, &Harmony::triads).unwrap();
+        assert_eq!(fretboard.lines().count(), 4);
+
+        let ukulele = Tuning::from_name("ukulele").unwrap();
+        let tab = ["g0", "c0", "e0", "a3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            from_tab_notation(&tab, &ukulele),
+            Some("G4 C4 E4 C5".to_owned())
+        );
+
+        let banjo = Tuning::from_name("banjo").unwrap();
+        let (fretboard, _, _) =
+            scale_on_fretboard("major", "g", &banjo, false, &window, &Harmony::triads).unwrap();
+        let short_string = fretboard.lines().last().unwrap().to_owned();
+        assert!(short_string.starts_with("   |  -   -   -   -   G"));
+    }
 } //
@@ -1,8 +1,10 @@
-use std::cmp;
+use std::sync::OnceLock;
 extern crate enum_utils;
 extern crate itertools;
 use super::note::Note;
+use super::spelling::Spelling;
 use itertools::Itertools;
+use std::fmt;
 
 /* Chords
  */
@@ -19,6 +21,54 @@ enum TriadType {
     plus,
 }
 impl TriadType {
+    fn intervals(&self) -> Vec<Interval> {
+        match self {
+            TriadType::minor => vec![Interval::MINOR_3, Interval::PERFECT_5],
+            TriadType::minor_omitted_5 => vec![Interval::MINOR_3],
+            TriadType::major => vec![Interval::MAJOR_3, Interval::PERFECT_5],
+            TriadType::major_omitted_5 => vec![Interval::MAJOR_3],
+            TriadType::sus2 => vec![Interval::MAJOR_2, Interval::PERFECT_5],
+            TriadType::sus4 => vec![Interval::PERFECT_4, Interval::PERFECT_5],
+            TriadType::minor_diminished => vec![Interval::MINOR_3, Interval::FLATTENED_5],
+            TriadType::major_diminished => vec![Interval::MAJOR_3, Interval::FLATTENED_5],
+            TriadType::plus => vec![Interval::MAJOR_3, Interval::AUGMENTED_5],
+        }
+    }
+    fn degrees(&self) -> Vec<i32> {
+        match self {
+            TriadType::minor_omitted_5 | TriadType::major_omitted_5 => vec![3],
+            TriadType::sus2 => vec![2, 5],
+            TriadType::sus4 => vec![4, 5],
+            _ => vec![3, 5],
+        }
+    }
+    fn without_fifth(&self) -> TriadType {
+        match self {
+            TriadType::minor => TriadType::minor_omitted_5,
+            TriadType::major => TriadType::major_omitted_5,
+            _ => self.clone(),
+        }
+    }
+    fn is_minor(&self) -> bool {
+        matches!(
+            self,
+            TriadType::minor | TriadType::minor_omitted_5 | TriadType::minor_diminished
+        )
+    }
+    fn from_third_and_fifth(third: Interval, fifth: Option<Interval>) -> Option<TriadType> {
+        match (third, fifth) {
+            (Interval::MINOR_3, Some(Interval::PERFECT_5)) => Some(TriadType::minor),
+            (Interval::MINOR_3, None) => Some(TriadType::minor_omitted_5),
+            (Interval::MAJOR_3, Some(Interval::PERFECT_5)) => Some(TriadType::major),
+            (Interval::MAJOR_3, None) => Some(TriadType::major_omitted_5),
+            (Interval::MAJOR_2, Some(Interval::PERFECT_5)) => Some(TriadType::sus2),
+            (Interval::PERFECT_4, Some(Interval::PERFECT_5)) => Some(TriadType::sus4),
+            (Interval::MINOR_3, Some(Interval::FLATTENED_5)) => Some(TriadType::minor_diminished),
+            (Interval::MAJOR_3, Some(Interval::FLATTENED_5)) => Some(TriadType::major_diminished),
+            (Interval::MAJOR_3, Some(Interval::AUGMENTED_5)) => Some(TriadType::plus),
+            _ => None,
+        }
+    }
     pub fn to_string(&self) -> &str {
         return match self {
             TriadType::minor | TriadType::minor_omitted_5 => "m",
@@ -26,7 +76,7 @@ impl TriadType {
             TriadType::sus2 => "sus2",
             TriadType::sus4 => "sus4",
             TriadType::minor_diminished => "dim",
-            TriadType::major_diminished => "maj-dim",
+            TriadType::major_diminished => "(b5)",
             TriadType::plus => "+",
         };
     }
@@ -97,12 +147,43 @@ impl Interval {
     const AUGMENTED_11: Interval = Interval {
         note: Note { semitones: 18 },
     };
-    const LOWER: Interval = Interval {
-        note: Note { semitones: 19 },
+    const FLATTENED_13: Interval = Interval {
+        note: Note { semitones: 20 },
+    };
+    const MAJOR_13: Interval = Interval {
+        note: Note { semitones: 21 },
     };
 
-    pub fn to_string(&self) -> &str {
-        return match *self {
+    fn degree(&self) -> i32 {
+        /* The scale degree an interval is written as when it is not
+         * part of the triad, e.g. 8 semitones are a minor sixth rather
+         * than an augmented fifth.
+         */
+        const DEGREES: [i32; 22] = [
+            1, 2, 2, 3, 3, 4, 5, 5, 6, 6, 7, 7, 8, 9, 9, 9, 11, 11, 11, 12, 13, 13,
+        ];
+        let semitones = self.note.semitones;
+        match semitones >= 0 && (semitones as usize) < DEGREES.len() {
+            true => DEGREES[semitones as usize],
+            false => DEGREES[semitones.rem_euclid(12) as usize],
+        }
+    }
+    fn symbol(&self) -> &'static str {
+        /* The name of a tone added to a chord symbol, e.g. the b9 of
+         * C7b9 or the #11 of Cmaj7#11.
+         */
+        match *self {
+            Interval::MINOR_2 => "b2",
+            Interval::MINOR_6 => "b6",
+            Interval::MINOR_9 => "b9",
+            Interval::PLUS_9 => "#9",
+            Interval::AUGMENTED_11 => "#11",
+            Interval::FLATTENED_13 => "b13",
+            _ => self.to_string(),
+        }
+    }
+    pub fn to_string(self) -> &'static str {
+        match self {
             Interval::OMITTED => "",
             Interval::MINOR_2 => "2m",
             Interval::MAJOR_2 => "2",
@@ -112,7 +193,6 @@ impl Interval {
             Interval::FLATTENED_5 => "5-",
             Interval::PERFECT_5 => "5",
             Interval::AUGMENTED_5 => "5+",
-            Interval::MINOR_6 => "6m",
             Interval::MAJOR_6 => "6",
             Interval::MINOR_7 => "7",
             /* for the seven, indicate the major as minor is default */
@@ -123,13 +203,14 @@ impl Interval {
             Interval::FLATTENED_11 => "11-",
             Interval::PERFECT_11 => "11",
             Interval::AUGMENTED_11 => "11+",
+            Interval::FLATTENED_13 => "13-",
+            Interval::MAJOR_13 => "13",
             _ => "blub",
-        };
+        }
     }
 }
 #[derive(Clone, PartialEq, Debug)]
 enum ChordType {
-    UNKNOWN,
     TwoTone {
         t: Interval,
     },
@@ -155,323 +236,1038 @@ enum ChordType {
         e2: Interval,
         e3: Interval,
     },
+    ThirteenChord {
+        t: TriadType,
+        e1: Interval,
+        e2: Interval,
+        e3: Interval,
+        e4: Interval,
+    },
+}
+impl ChordType {
+    fn intervals(&self) -> Vec<Interval> {
+        /* All intervals above the root, omitted extensions are skipped.
+         */
+        self.intervals_and_degrees()
+            .into_iter()
+            .map(|(i, _)| i)
+            .collect()
+    }
+    fn intervals_and_degrees(&self) -> Vec<(Interval, i32)> {
+        let (t, extensions) = match self {
+            ChordType::TwoTone { t } => return vec![(*t, t.degree())],
+            ChordType::Triad { t } => (t, vec![]),
+            ChordType::AddChord { t, e } => (t, vec![*e]),
+            ChordType::SevenChord { t, e } => (t, vec![*e]),
+            ChordType::NineChord { t, e1, e2 } => (t, vec![*e1, *e2]),
+            ChordType::ElevenChord { t, e1, e2, e3 } => (t, vec![*e1, *e2, *e3]),
+            ChordType::ThirteenChord { t, e1, e2, e3, e4 } => (t, vec![*e1, *e2, *e3, *e4]),
+        };
+        let extension_degree = |e: &Interval| match (t, *e) {
+            // the diminished seventh
+            (TriadType::minor_diminished, Interval::MAJOR_6) if self.is_seventh() => 7,
+            _ => e.degree(),
+        };
+        t.intervals()
+            .into_iter()
+            .zip(t.degrees())
+            .chain(extensions.iter().map(|e| (*e, extension_degree(e))))
+            .filter(|(i, _)| *i != Interval::OMITTED)
+            .collect()
+    }
+    fn pitch_class_set(&self) -> u16 {
+        /* The root and the tones above it as a 12 bit set, bit 0
+         * being the root.
+         */
+        self.intervals()
+            .iter()
+            .fold(1, |set, i| set | 1 << i.note.pitch_class().semitones)
+    }
+    fn without_fifth(&self) -> ChordType {
+        match self {
+            ChordType::TwoTone { .. } => self.clone(),
+            ChordType::Triad { t } => ChordType::Triad {
+                t: t.without_fifth(),
+            },
+            ChordType::AddChord { t, e } => ChordType::AddChord {
+                t: t.without_fifth(),
+                e: *e,
+            },
+            ChordType::SevenChord { t, e } => ChordType::SevenChord {
+                t: t.without_fifth(),
+                e: *e,
+            },
+            ChordType::NineChord { t, e1, e2 } => ChordType::NineChord {
+                t: t.without_fifth(),
+                e1: *e1,
+                e2: *e2,
+            },
+            ChordType::ElevenChord { t, e1, e2, e3 } => ChordType::ElevenChord {
+                t: t.without_fifth(),
+                e1: *e1,
+                e2: *e2,
+                e3: *e3,
+            },
+            ChordType::ThirteenChord { t, e1, e2, e3, e4 } => ChordType::ThirteenChord {
+                t: t.without_fifth(),
+                e1: *e1,
+                e2: *e2,
+                e3: *e3,
+                e4: *e4,
+            },
+        }
+    }
+    fn alterations(&self) -> usize {
+        /* the number of altered fifths and tensions, e.g. 2 for 7#5#9 */
+        self.intervals_and_degrees()
+            .iter()
+            .filter(|(i, degree)| {
+                matches!(
+                    (i.note.semitones, degree),
+                    (6, 5) | (8, 5) | (13, _) | (15, _) | (18, _) | (20, _)
+                )
+            })
+            .count()
+    }
+    fn is_seventh(&self) -> bool {
+        matches!(self, ChordType::SevenChord { .. })
+    }
+    fn is_minor(&self) -> bool {
+        match self {
+            ChordType::TwoTone { .. } => false,
+            ChordType::Triad { t }
+            | ChordType::AddChord { t, .. }
+            | ChordType::SevenChord { t, .. }
+            | ChordType::NineChord { t, .. }
+            | ChordType::ElevenChord { t, .. }
+            | ChordType::ThirteenChord { t, .. } => t.is_minor(),
+        }
+    }
+}
+/* Chord recognition
+ * A chord is matched as the set of its pitch classes above a root
+ * against a table of templates, one 12 bit set per chord type.
+ */
+struct Template {
+    type_: ChordType,
+    mask: u16,
+    /* the perfect fifth may be left out, e.g. in C7 or Cm9 */
+    fifth_omissible: bool,
+}
+struct TemplateMatch {
+    type_: ChordType,
+    /* the position in the template table */
+    rank: usize,
+    omitted: usize,
+    /* the chord tones besides the fifth that are not played */
+    missing: u16,
+    /* the played pitch classes the template leaves unexplained */
+    extra: u16,
+}
+pub struct ChordMatch {
+    pub chord: Chord,
+    /* 100 for all notes making a chord on the bass note */
+    pub score: i32,
+    /* other chord tones that are not played */
+    pub missing: Vec<Note>,
+    /* played notes that are no chord tones */
+    pub extra: Vec<Note>,
+}
+impl fmt::Display for ChordMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        /* The chord name followed by the notes it doesn't explain and
+         * the ones it lacks, spelled from its root, e.g. "Cmaj7 +F# -E".
+         */
+        let root = self.chord.root_spelling();
+        let spell = |sign: &str, n: &Note| {
+            let interval = Interval {
+                note: (*n - self.chord.notes[0]).pitch_class(),
+            };
+            let spelling = root.above(interval.note.semitones, interval.degree());
+            format!(" {}{}", sign, spelling.capitalized())
+        };
+        write!(
+            f,
+            "{}{}{}",
+            self.chord,
+            self.extra.iter().map(|n| spell("+", n)).collect::<String>(),
+            self.missing
+                .iter()
+                .map(|n| spell("-", n))
+                .collect::<String>()
+        )
+    }
+}
+/* Voicings that leave the root to the bass player: the third and
+ * seventh only (shell), or with the fifth and extensions (rootless).
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RootlessVoicing {
+    shell,
+    rootless,
 }
 #[derive(Clone)]
 pub struct Chord {
     type_: ChordType,
     notes: Vec<Note>,
+    bass: Option<Note>,
+    rootless: Option<RootlessVoicing>,
 }
 impl Chord {
-    fn build_inversions(intervals: &Vec<Note>) -> Vec<Vec<Note>> {
-        let mut octaves_spanned = 1;
-        if !intervals.is_empty() {
-            let span = *intervals.iter().max().unwrap() - *intervals.iter().min().unwrap();
-            octaves_spanned = cmp::max((span.semitones as f64 / 12 as f64).ceil() as i32, 1);
-        }
-        let n_notes = intervals.len(); // n-1 inversion
-        let mut result: Vec<Vec<Note>> = vec![];
-        for i in 1..n_notes {
-            let front = intervals
-                .iter()
-                .take(i)
-                .map(|s| *s + octaves_spanned * Note::octave());
-            let back = intervals.iter().skip(i).take(n_notes - i).map(|s| *s);
-            let inversion_intervals = back
-                .chain(front)
-                .unique_by(|s| s.no_octaves())
-                .collect::<Vec<_>>();
-            result.push(inversion_intervals);
-        }
-        return result;
-    }
-    pub fn to_string(&self) -> String {
+    pub fn to_string_with_root(&self, root: &Spelling) -> String {
+        /* The root is passed in so that chords can be named in the
+         * spelling of a key, e.g. A#m in F# major but Bbm in Db major.
+         */
         let type_str = match &self.type_ {
-            ChordType::UNKNOWN => format!("Unknown"),
             ChordType::TwoTone { t } => format!("{}", t.to_string()),
             ChordType::Triad { t } => format!("{}", t.to_string()),
-            ChordType::AddChord { t, e } => format!("{}add{}", t.to_string(), e.to_string()),
-            ChordType::SevenChord { t, e } => format!("{}{}", t.to_string(), e.to_string()),
-            ChordType::NineChord { t, e1, e2 } => format!("{}{}", t.to_string(), e2.to_string()),
-            ChordType::ElevenChord { t, e1, e2, e3 } => format!("11{}", t.to_string()),
+            /* the sixth goes before a sus, e.g. G6sus4 */
+            ChordType::AddChord {
+                t: t @ (TriadType::sus2 | TriadType::sus4),
+                e: Interval::MAJOR_6,
+            } => format!("6{}", t.to_string()),
+            ChordType::AddChord {
+                t,
+                e: Interval::MAJOR_6,
+            } => format!("{}6", t.to_string()),
+            ChordType::AddChord {
+                t,
+                e: Interval::MINOR_6,
+            } => format!("{}(b6)", t.to_string()),
+            ChordType::AddChord { t, e } => format!("{}add{}", t.to_string(), e.symbol()),
+            ChordType::SevenChord {
+                t: TriadType::minor_diminished,
+                e: Interval::MAJOR_6,
+            } => "dim7".to_string(),
+            ChordType::SevenChord { t, e } => Chord::extended_name(t, *e, &[Interval::OMITTED; 3]),
+            ChordType::NineChord {
+                t: t @ (TriadType::sus2 | TriadType::sus4),
+                e1: Interval::MAJOR_6,
+                e2,
+            } => format!("6/{}{}", e2.symbol(), t.to_string()),
+            ChordType::NineChord {
+                t,
+                e1: Interval::MAJOR_6,
+                e2,
+            } => format!("{}6/{}", t.to_string(), e2.symbol()),
+            ChordType::NineChord { t, e1, e2 } => {
+                Chord::extended_name(t, *e1, &[*e2, Interval::OMITTED, Interval::OMITTED])
+            }
+            ChordType::ElevenChord { t, e1, e2, e3 } => {
+                Chord::extended_name(t, *e1, &[*e2, *e3, Interval::OMITTED])
+            }
+            ChordType::ThirteenChord { t, e1, e2, e3, e4 } => {
+                Chord::extended_name(t, *e1, &[*e2, *e3, *e4])
+            }
+        };
+        let spelling = self.spelling(root);
+        let bass_str = match self.bass {
+            Some(_) => format!("/{}", spelling.last().unwrap().capitalized()),
+            None => String::from(""),
+        };
+        let rootless_str = match self.rootless {
+            Some(voicing) => format!(" ({:?})", voicing),
+            None => String::from(""),
+        };
+        format!(
+            "{}{}{}{}",
+            root.capitalized(),
+            type_str,
+            bass_str,
+            rootless_str
+        )
+    }
+    fn extended_name(t: &TriadType, seventh: Interval, extensions: &[Interval]) -> String {
+        /* Names seventh chords and their extensions in the usual way:
+         * the highest natural extension gives the number (Cm11, C13),
+         * altered and skipped ones follow (C7b9b13, Cmaj7#11, C7add11,
+         * C7add13).
+         */
+        let (ninth, eleventh, thirteenth) = (extensions[0], extensions[1], extensions[2]);
+        let mut number = 7;
+        let mut alterations = vec![];
+        match *t {
+            TriadType::minor_diminished | TriadType::major_diminished => alterations.push("b5"),
+            TriadType::plus => alterations.push("#5"),
+            _ => {}
+        };
+        match ninth {
+            Interval::MAJOR_9 => number = 9,
+            Interval::OMITTED => {}
+            _ => alterations.push(ninth.symbol()),
+        };
+        match eleventh {
+            Interval::PERFECT_11 if number == 9 => number = 11,
+            Interval::PERFECT_11 => alterations.push("add11"),
+            Interval::OMITTED => {}
+            _ => alterations.push(eleventh.symbol()),
         };
-        return format!("{}{}", self.notes[0].to_string().to_uppercase(), type_str);
+        match thirteenth {
+            Interval::MAJOR_13 if number > 7 => number = 13,
+            Interval::MAJOR_13 => alterations.push("add13"),
+            Interval::OMITTED => {}
+            _ => alterations.push(thirteenth.symbol()),
+        };
+        let third = match t {
+            TriadType::minor | TriadType::minor_omitted_5 | TriadType::minor_diminished => "m",
+            _ => "",
+        };
+        let major_seventh = match (seventh, third) {
+            (Interval::MAJOR_7, "m") => "Maj",
+            (Interval::MAJOR_7, _) => "maj",
+            _ => "",
+        };
+        let sus = match t {
+            TriadType::sus2 | TriadType::sus4 => t.to_string(),
+            _ => "",
+        };
+        format!(
+            "{}{}{}{}{}",
+            third,
+            major_seventh,
+            number,
+            sus,
+            alterations.join("")
+        )
+    }
+    pub fn root_spelling(&self) -> Spelling {
+        Spelling::of_root(self.notes[0], self.type_.is_minor())
+    }
+    pub fn spelling(&self, root: &Spelling) -> Vec<Spelling> {
+        /* Chord tones spelled in thirds from the root, e.g. Db F Ab
+         * rather than C# F G#. A slash bass comes last.
+         */
+        let mut notes = vec![*root];
+        notes.extend(
+            self.type_
+                .intervals_and_degrees()
+                .iter()
+                .map(|(i, degree)| root.above(i.note.semitones, *degree)),
+        );
+        if let Some(bass) = self.bass {
+            /* a chord tone in the bass keeps its spelling, e.g. the
+             * F# of Cmaj7#11/F#
+             */
+            let interval = Interval {
+                note: (bass - self.notes[0]).pitch_class(),
+            };
+            let spelling = match notes.iter().find(|n| n.pitch_class() == bass.pitch_class()) {
+                Some(tone) => *tone,
+                None => root.above(interval.note.semitones, interval.degree()),
+            };
+            notes.push(spelling);
+        }
+        notes
     }
     pub fn get_notes(&self) -> &Vec<Note> {
         return &self.notes;
     }
-    fn from_intervals(root: Note, intervals: &Vec<Note>, type_: ChordType) -> Chord {
-        let mut notes = intervals
-            .iter()
-            .map(|x| *x)
-            .scan(root, |state, n| {
-                *state = *state + n;
-                return Some(*state);
-            })
-            .collect::<Vec<_>>();
-        notes.insert(0, root);
-        return Chord {
-            notes: notes,
-            type_: type_,
+    pub fn transposed(&self, semitones: i32) -> Chord {
+        let shift = Note { semitones };
+        Chord {
+            type_: self.type_.clone(),
+            notes: self.notes.iter().map(|n| *n + shift).collect(),
+            bass: self.bass.map(|b| b + shift),
+            rootless: self.rootless,
+        }
+    }
+    pub fn get_bass(&self) -> Option<Note> {
+        self.bass
+    }
+    pub fn get_rootless(&self) -> Option<RootlessVoicing> {
+        self.rootless
+    }
+    pub fn with_bass(&self, bass: Note) -> Chord {
+        /* The same chord over another bass note, e.g. an inversion
+         * found by find_chord.
+         */
+        let mut chord = self.clone();
+        chord.bass = match bass.pitch_class() == self.notes[0].pitch_class() {
+            true => None,
+            false => Some(bass),
         };
+        chord
     }
-    fn intervals(&self) -> Vec<Note> {
-        let mut notes = self.notes.clone();
-        notes.sort();
-        return notes
-            .windows(2)
-            .map(|slice| (slice[1] - slice[0]))
+    pub fn is_dominant(&self) -> bool {
+        /* a major third above the root, neither a major seventh nor
+         * an augmented fifth
+         */
+        let root = self.notes[0];
+        let intervals = self
+            .notes
+            .iter()
+            .map(|n| (*n - root).pitch_class().semitones)
             .collect::<Vec<_>>();
+        intervals.contains(&4)
+            && !intervals.contains(&11)
+            && (intervals.contains(&7) || !intervals.contains(&8))
     }
-
-    fn match_chord(intervals: Vec<Note>) -> ChordType {
-        /* Assumptions
-         * 1. Intervals has been root-subtracted
-         * 2. sorted
-         * 3. made unique
+    pub fn is_interval(&self) -> bool {
+        /* two notes other than a power chord, e.g. C2 */
+        match self.type_ {
+            ChordType::TwoTone { t } => t != Interval::PERFECT_5,
+            _ => false,
+        }
+    }
+    pub fn omissible_tones(&self) -> Vec<Note> {
+        /* Tones a voicing may leave out: the perfect fifth of a chord
+         * of four tones or more, and the eleventh of a dominant
+         * thirteenth, where it clashes with the third.
          */
-        let sequence = intervals
+        let root = self.notes[0];
+        let dominant_13 =
+            matches!(self.type_, ChordType::ThirteenChord { .. }) && self.is_dominant();
+        self.type_
+            .intervals_and_degrees()
             .iter()
-            .skip(1) // skip 0
-            .map(|n| Interval { note: *n })
-            .collect::<Vec<Interval>>();
-        let mut candidate = ChordType::UNKNOWN;
-        for interval in sequence {
-            candidate = match interval {
-                Interval::MINOR_2 => ChordType::TwoTone { t: interval },
-                Interval::MAJOR_2 => match candidate {
-                    ChordType::UNKNOWN => ChordType::TwoTone { t: interval },
-                    _ => ChordType::UNKNOWN,
-                },
-                Interval::MINOR_3 => match candidate {
-                    ChordType::UNKNOWN => ChordType::Triad {
-                        t: TriadType::minor_omitted_5,
-                    },
-                    ChordType::TwoTone { t } => ChordType::AddChord {
-                        t: TriadType::minor_omitted_5,
-                        e: t,
-                    },
-                    _ => ChordType::UNKNOWN,
-                },
-                Interval::MAJOR_3 => match candidate {
-                    ChordType::UNKNOWN => ChordType::Triad {
-                        t: TriadType::major_omitted_5,
-                    },
-                    ChordType::TwoTone { t } => ChordType::AddChord {
-                        t: TriadType::major_omitted_5,
-                        e: t,
-                    },
-                    _ => ChordType::UNKNOWN,
-                },
-                Interval::PERFECT_4 => match candidate {
-                    ChordType::UNKNOWN => ChordType::TwoTone { t: interval },
-                    ChordType::Triad { t } => ChordType::AddChord { t: t, e: interval },
-                    _ => ChordType::UNKNOWN,
-                },
-                Interval::FLATTENED_5 => match candidate {
-                    ChordType::UNKNOWN => ChordType::TwoTone { t: interval },
-                    ChordType::Triad {
-                        t: TriadType::minor_omitted_5,
-                    } => ChordType::Triad {
-                        t: TriadType::minor_diminished,
-                    },
-                    ChordType::Triad {
-                        t: TriadType::major_omitted_5,
-                    } => ChordType::Triad {
-                        t: TriadType::major_diminished,
-                    },
-                    _ => ChordType::UNKNOWN,
-                },
-                Interval::PERFECT_5 => match candidate {
-                    ChordType::UNKNOWN => ChordType::TwoTone { t: interval },
-                    ChordType::Triad {
-                        t: TriadType::minor_omitted_5,
-                    } => ChordType::Triad {
-                        t: TriadType::minor,
-                    },
-                    ChordType::Triad {
-                        t: TriadType::major_omitted_5,
-                    } => ChordType::Triad {
-                        t: TriadType::major,
-                    },
-                    ChordType::AddChord {
-                        t: TriadType::minor_omitted_5,
-                        e,
-                    } => ChordType::AddChord {
-                        t: TriadType::minor,
-                        e,
-                    },
-                    ChordType::AddChord {
-                        t: TriadType::major_omitted_5,
-                        e,
-                    } => ChordType::AddChord {
-                        t: TriadType::major,
-                        e,
-                    },
-                    ChordType::TwoTone {
-                        t: Interval::MAJOR_2,
-                    } => ChordType::Triad { t: TriadType::sus2 },
-                    ChordType::TwoTone {
-                        t: Interval::PERFECT_4,
-                    } => ChordType::Triad { t: TriadType::sus4 },
-                    _ => ChordType::UNKNOWN,
-                },
-                Interval::AUGMENTED_5 => match candidate {
-                    ChordType::UNKNOWN => ChordType::TwoTone { t: interval },
-                    ChordType::Triad {
-                        t: TriadType::major_omitted_5,
-                    } => ChordType::Triad { t: TriadType::plus },
-                    _ => ChordType::UNKNOWN,
-                },
-                Interval::MINOR_6 | Interval::MAJOR_6 => match candidate {
-                    ChordType::UNKNOWN => ChordType::TwoTone { t: interval },
-                    ChordType::Triad { t } => ChordType::AddChord { t: t, e: interval },
-                    _ => ChordType::UNKNOWN,
-                },
-                Interval::MINOR_7 | Interval::MAJOR_7 => match candidate {
-                    ChordType::UNKNOWN => ChordType::TwoTone { t: interval },
-                    ChordType::Triad { t } => ChordType::SevenChord { t: t, e: interval },
-                    // disallow sus27 or sus47 ? explicitly disallow dim maj7 ?
-                    _ => ChordType::UNKNOWN,
-                },
-                Interval::MINOR_9 | Interval::MAJOR_9 => match candidate {
-                    ChordType::UNKNOWN => ChordType::TwoTone { t: interval },
-                    ChordType::Triad { t } => ChordType::AddChord { t: t, e: interval },
-                    ChordType::SevenChord { t, e } => ChordType::NineChord {
-                        t,
-                        e1: e,
-                        e2: interval,
-                    },
-                    _ => ChordType::UNKNOWN,
-                },
-                Interval::PLUS_9 => match candidate {
-                    ChordType::UNKNOWN => ChordType::TwoTone { t: interval },
-                    ChordType::Triad { t } => ChordType::AddChord { t: t, e: interval },
-                    ChordType::SevenChord { t, e } => ChordType::NineChord {
-                        t,
-                        e1: e,
-                        e2: interval,
-                    },
-                    _ => ChordType::UNKNOWN,
-                },
-                Interval::PERFECT_11 => match candidate {
-                    ChordType::UNKNOWN => ChordType::TwoTone { t: interval },
-                    ChordType::Triad { t } => ChordType::AddChord { t: t, e: interval },
-                    ChordType::SevenChord { t, e } => ChordType::ElevenChord {
-                        t,
-                        e1: e,
-                        e2: Interval::OMITTED,
-                        e3: interval,
-                    },
-                    ChordType::NineChord { t, e1, e2 } => ChordType::ElevenChord {
-                        t,
-                        e1: e1,
-                        e2: e2,
-                        e3: interval,
-                    },
-                    _ => ChordType::UNKNOWN,
-                },
-                _ => ChordType::UNKNOWN, //
-            };
-            // no transition out of unknown
-            if candidate == ChordType::UNKNOWN {
-                break;
-            }
+            .filter(|(i, degree)| match (i.note.semitones, degree) {
+                (7, 5) => self.notes.len() > 3,
+                (17, 11) => dominant_13,
+                _ => false,
+            })
+            .map(|(i, _)| root + i.note)
+            .collect()
+    }
+    pub fn interval_names(&self) -> Vec<&'static str> {
+        let mut names = vec!["1"];
+        names.extend(self.type_.intervals().iter().map(|i| i.to_string()));
+        names
+    }
+    fn from_type(root: Note, type_: ChordType, bass: Option<Note>) -> Chord {
+        let mut notes = vec![root];
+        notes.extend(type_.intervals().iter().map(|i| root + i.note));
+        Chord {
+            notes,
+            type_,
+            bass,
+            rootless: None,
         }
-        return candidate;
     }
+    fn split_root(symbol: &str) -> Option<(Note, &str)> {
+        /* Splits a leading note name (e.g. "Bb" in "Bb13") from the rest.
+         */
+        let (root, rest) = Spelling::split_name(symbol)?;
+        Some((root.pitch_class(), rest))
+    }
+    fn split_number(symbol: &str) -> Option<(i32, &str)> {
+        let n_digits = symbol.chars().take_while(|c| c.is_ascii_digit()).count();
+        let number = symbol[..n_digits].parse::<i32>().ok()?;
+        Some((number, &symbol[n_digits..]))
+    }
+    fn parse_quality(quality: &str) -> Option<ChordType> {
+        /* Reads the part of a chord symbol after the root, e.g. "m7b5",
+         * "13#11", "sus4add9" or "maj9", and stacks the intervals it names.
+         */
+        let cleaned = quality
+            .chars()
+            .filter(|c| !"() ,".contains(*c))
+            .collect::<String>();
+        let mut rest = &cleaned[..];
+        let mut third = Some(Interval::MAJOR_3);
+        let mut fifth = Some(Interval::PERFECT_5);
+        let mut sixth: Option<Interval> = None;
+        let mut seventh: Option<Interval> = None;
+        let mut ninth: Option<Interval> = None;
+        let mut eleventh: Option<Interval> = None;
+        let mut thirteenth: Option<Interval> = None;
+        let mut added: Option<Interval> = None;
+        let mut major_seventh = false;
+        let mut power_chord = false;
+        let is_start = |rest: &str| rest.len() == cleaned.len();
 
-    pub fn find_chord(notes: &Vec<Note>) -> Vec<Option<Chord>> {
-        let mut results: Vec<Option<Chord>> = Vec::new();
-        let mut steps_to_root = vec![notes
-            .iter()
-            .map(|s| *s)
-            .unique_by(|s1| s1.no_octaves()) // delete duplicate notes
-            .collect::<Vec<_>>()];
-        steps_to_root.sort();
-        let mut inversions = Chord::build_inversions(&steps_to_root[0]);
-        steps_to_root.append(&mut inversions);
-
-        for inversion in &mut steps_to_root {
-            let root = inversion.iter().min().unwrap();
-
-            // build relative semitone steps
-            let mut intervals = inversion
+        while !rest.is_empty() {
+            if let Some(r) = ["maj", "Maj", "MA", "M"]
                 .iter()
-                .map(|s| {
-                    let diff = *s - *root;
-                    if diff > Interval::AUGMENTED_11.note {
-                        diff.no_octaves()
-                    } else {
-                        diff
+                .find_map(|t| rest.strip_prefix(t))
+            {
+                major_seventh = true;
+                rest = r;
+            } else if let Some(r) = ["Δ", "^"].iter().find_map(|t| rest.strip_prefix(t)) {
+                major_seventh = true;
+                seventh = Some(Interval::MAJOR_7);
+                rest = r;
+            } else if let Some(r) = ["min", "mi", "m", "-"]
+                .iter()
+                .find_map(|t| rest.strip_prefix(t))
+            {
+                third = Some(Interval::MINOR_3);
+                rest = r;
+            } else if let Some(r) = ["no", "omit"].iter().find_map(|t| rest.strip_prefix(t)) {
+                let (number, r) = Chord::split_number(r)?;
+                match number {
+                    3 => third = None,
+                    5 => fifth = None,
+                    _ => return None,
+                };
+                rest = r;
+            } else if let Some(r) = ["dim", "°", "o"].iter().find_map(|t| rest.strip_prefix(t)) {
+                third = Some(Interval::MINOR_3);
+                fifth = Some(Interval::FLATTENED_5);
+                rest = r;
+                if let Some(r) = rest.strip_prefix("7") {
+                    // the diminished seventh is enharmonic to the major sixth
+                    seventh = Some(Interval::MAJOR_6);
+                    rest = r;
+                }
+            } else if let Some(r) = ["ø7", "ø"].iter().find_map(|t| rest.strip_prefix(t)) {
+                third = Some(Interval::MINOR_3);
+                fifth = Some(Interval::FLATTENED_5);
+                seventh = Some(Interval::MINOR_7);
+                rest = r;
+            } else if let Some(r) = ["aug", "+"].iter().find_map(|t| rest.strip_prefix(t)) {
+                fifth = Some(Interval::AUGMENTED_5);
+                rest = r;
+                if let Some(r) = rest.strip_prefix("5") {
+                    rest = r;
+                }
+            } else if let Some(r) = rest.strip_prefix("sus") {
+                let (number, r) = Chord::split_number(r).unwrap_or((4, r));
+                third = match number {
+                    2 => Some(Interval::MAJOR_2),
+                    4 => Some(Interval::PERFECT_4),
+                    _ => return None,
+                };
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix("add") {
+                let (accidental, r) = match r.chars().next() {
+                    Some('b') => (-1, &r[1..]),
+                    Some('#') => (1, &r[1..]),
+                    _ => (0, r),
+                };
+                let (number, r) = Chord::split_number(r)?;
+                added = match (accidental, number) {
+                    (0, 2) => Some(Interval::MAJOR_2),
+                    (0, 4) => Some(Interval::PERFECT_4),
+                    (0, 6) => Some(Interval::MAJOR_6),
+                    (-1, 9) => Some(Interval::MINOR_9),
+                    (0, 9) => Some(Interval::MAJOR_9),
+                    (1, 9) => Some(Interval::PLUS_9),
+                    (0, 11) => Some(Interval::PERFECT_11),
+                    (1, 11) => Some(Interval::AUGMENTED_11),
+                    (-1, 13) => Some(Interval::FLATTENED_13),
+                    (0, 13) => Some(Interval::MAJOR_13),
+                    _ => return None,
+                };
+                rest = r;
+            } else if rest.starts_with('b') || rest.starts_with('#') {
+                let flat = rest.starts_with('b');
+                let (number, r) = Chord::split_number(&rest[1..])?;
+                match (flat, number) {
+                    (true, 5) => fifth = Some(Interval::FLATTENED_5),
+                    (false, 5) => fifth = Some(Interval::AUGMENTED_5),
+                    (true, 6) => sixth = Some(Interval::MINOR_6),
+                    (true, 9) => ninth = Some(Interval::MINOR_9),
+                    (false, 9) => ninth = Some(Interval::PLUS_9),
+                    (false, 11) => eleventh = Some(Interval::AUGMENTED_11),
+                    (true, 13) => thirteenth = Some(Interval::FLATTENED_13),
+                    _ => return None,
+                };
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix("69") {
+                sixth = Some(Interval::MAJOR_6);
+                ninth = Some(Interval::MAJOR_9);
+                rest = r;
+            } else if let Some((number, r)) = Chord::split_number(rest) {
+                let seventh_interval = match major_seventh {
+                    true => Interval::MAJOR_7,
+                    false => Interval::MINOR_7,
+                };
+                match number {
+                    5 if is_start(rest) => power_chord = true,
+                    2 => added = Some(Interval::MAJOR_2),
+                    6 => sixth = Some(Interval::MAJOR_6),
+                    7 => seventh = Some(seventh_interval),
+                    9 | 11 | 13 => {
+                        seventh = Some(seventh_interval);
+                        ninth = ninth.or(Some(Interval::MAJOR_9));
+                        if number == 11 {
+                            eleventh = eleventh.or(Some(Interval::PERFECT_11));
+                        }
+                        if number == 13 {
+                            thirteenth = Some(Interval::MAJOR_13);
+                        }
                     }
-                })
-                .collect::<Vec<_>>();
-            intervals.sort(); //sort again - partial no octaves may have upended order
-            let mut type_ = Chord::match_chord(intervals.clone());
-            if type_ == ChordType::UNKNOWN {
-                intervals = intervals.into_iter().map(|n| n.no_octaves()).collect();
-                intervals.sort(); //sort again - no octaves may have upended order
-                type_ = Chord::match_chord(intervals.clone());
+                    _ => return None,
+                };
+                rest = r;
+            } else {
+                return None;
             }
-            match type_ {
-                ChordType::UNKNOWN => {
-                    results.push(None);
+        }
+        if power_chord {
+            return Some(ChordType::TwoTone {
+                t: Interval::PERFECT_5,
+            });
+        }
+        let t = TriadType::from_third_and_fifth(third?, fifth)?;
+        // an added tone on top of a seventh chord takes its regular slot
+        if seventh.is_some() {
+            match added {
+                Some(Interval::MAJOR_2) => ninth = ninth.or(Some(Interval::MAJOR_9)),
+                Some(Interval::PERFECT_4) => eleventh = eleventh.or(Some(Interval::PERFECT_11)),
+                Some(Interval::MAJOR_6) => thirteenth = thirteenth.or(Some(Interval::MAJOR_13)),
+                Some(e) if e.note.semitones < Interval::PERFECT_11.note.semitones => {
+                    ninth = ninth.or(Some(e))
                 }
-                _ => {
-                    results.push(Some(Chord::from_intervals(root.clone(), &intervals, type_)));
+                Some(e) if e.note.semitones < Interval::FLATTENED_13.note.semitones => {
+                    eleventh = eleventh.or(Some(e))
                 }
+                Some(e) => thirteenth = thirteenth.or(Some(e)),
+                None => {}
+            };
+            if let Some(sixth) = sixth {
+                thirteenth = thirteenth.or(Some(Interval {
+                    note: sixth.note + Interval::OCTAVE.note,
+                }));
             }
         }
-        return results;
+        let or_omitted = |e: Option<Interval>| e.unwrap_or(Interval::OMITTED);
+        match (seventh, sixth, added) {
+            (Some(e1), _, _) if thirteenth.is_some() => Some(ChordType::ThirteenChord {
+                t,
+                e1,
+                e2: or_omitted(ninth),
+                e3: or_omitted(eleventh),
+                e4: thirteenth.unwrap(),
+            }),
+            (Some(e1), _, _) if eleventh.is_some() => Some(ChordType::ElevenChord {
+                t,
+                e1,
+                e2: or_omitted(ninth),
+                e3: eleventh.unwrap(),
+            }),
+            (Some(e1), _, _) if ninth.is_some() => Some(ChordType::NineChord {
+                t,
+                e1,
+                e2: ninth.unwrap(),
+            }),
+            (Some(e), _, _) => Some(ChordType::SevenChord { t, e }),
+            (None, Some(sixth), None) => match (ninth, eleventh, thirteenth) {
+                (Some(e2), None, None) => Some(ChordType::NineChord { t, e1: sixth, e2 }),
+                (None, None, None) => Some(ChordType::AddChord { t, e: sixth }),
+                _ => None,
+            },
+            (None, None, Some(e)) => match (ninth, eleventh, thirteenth) {
+                (None, None, None) => Some(ChordType::AddChord { t, e }),
+                _ => None,
+            },
+            (None, None, None) => match (ninth, eleventh, thirteenth) {
+                (None, None, None) => Some(ChordType::Triad { t }),
+                (Some(e), None, None) | (None, Some(e), None) | (None, None, Some(e)) => {
+                    Some(ChordType::AddChord { t, e })
+                }
+                _ => None,
+            },
+            _ => None,
+        }
     }
-}
-#[test]
-fn test_first_inversion() {
-    let intervals = [
-        Note { semitones: 0 },
-        Interval::MINOR_3.note,
-        Interval::PERFECT_5.note,
-    ]
-    .iter()
-    .map(|n| *n)
-    .collect::<Vec<_>>();
-    let minor_inversions = Chord::build_inversions(&intervals);
-    assert_eq!(
-        minor_inversions[0],
-        vec![3, 7, 12]
+    pub fn from_symbol(symbol: &str) -> Option<Chord> {
+        /* Parses a chord symbol such as F#m7b5, Bb13#11, C/G or Dsus4add9.
+         * The inverse of find_chord followed by to_string.
+         */
+        let symbol = symbol.trim().replace("6/9", "69");
+        let (body, bass_str) = match symbol.find('/') {
+            Some(pos) => (&symbol[..pos], Some(&symbol[pos + 1..])),
+            None => (&symbol[..], None),
+        };
+        let (root, quality) = Chord::split_root(body)?;
+        let bass = match bass_str {
+            Some(s) => match Chord::split_root(s)? {
+                (note, "") => Some(note),
+                _ => return None,
+            },
+            None => None,
+        };
+        let type_ = Chord::parse_quality(quality)?;
+        Some(Chord::from_type(root, type_, bass))
+    }
+    fn templates() -> &'static Vec<Template> {
+        /* Every chord type the matcher knows, simplest first, so that
+         * a pitch class set is named by the first template it fits.
+         */
+        static TEMPLATES: OnceLock<Vec<Template>> = OnceLock::new();
+        TEMPLATES.get_or_init(|| {
+            let triads = [
+                TriadType::major,
+                TriadType::minor,
+                TriadType::sus4,
+                TriadType::sus2,
+                TriadType::minor_diminished,
+                TriadType::major_diminished,
+                TriadType::plus,
+            ];
+            let sevenths = [Interval::MINOR_7, Interval::MAJOR_7];
+            let mut types = vec![];
+            types.extend(triads.iter().map(|t| ChordType::Triad { t: t.clone() }));
+            for (t, e) in triads.iter().cartesian_product(sevenths.iter()) {
+                match (t, *e) {
+                    (TriadType::minor_diminished, Interval::MAJOR_7)
+                    | (TriadType::major_diminished, Interval::MAJOR_7) => continue,
+                    _ => types.push(ChordType::SevenChord {
+                        t: t.clone(),
+                        e: *e,
+                    }),
+                };
+            }
+            types.push(ChordType::SevenChord {
+                t: TriadType::minor_diminished,
+                e: Interval::MAJOR_6,
+            });
+            for t in &triads[..4] {
+                for e in &[
+                    Interval::MAJOR_6,
+                    Interval::MAJOR_9,
+                    Interval::PERFECT_11,
+                    Interval::AUGMENTED_11,
+                    Interval::MINOR_6,
+                    Interval::MINOR_9,
+                    Interval::PLUS_9,
+                ] {
+                    /* a minor triad with a minor sixth is a major
+                     * seventh chord over its third, e.g. Em(b6) is Cmaj7/E
+                     */
+                    if (t, *e) == (&TriadType::minor, Interval::MINOR_6) {
+                        continue;
+                    }
+                    types.push(ChordType::AddChord {
+                        t: t.clone(),
+                        e: *e,
+                    });
+                }
+            }
+            for t in &triads[..2] {
+                types.push(ChordType::NineChord {
+                    t: t.clone(),
+                    e1: Interval::MAJOR_6,
+                    e2: Interval::MAJOR_9,
+                });
+            }
+            let ninths = [
+                Interval::OMITTED,
+                Interval::MINOR_9,
+                Interval::MAJOR_9,
+                Interval::PLUS_9,
+            ];
+            let elevenths = [
+                Interval::OMITTED,
+                Interval::PERFECT_11,
+                Interval::AUGMENTED_11,
+            ];
+            let thirteenths = [
+                Interval::OMITTED,
+                Interval::FLATTENED_13,
+                Interval::MAJOR_13,
+            ];
+            for (t, e1) in triads.iter().cartesian_product(sevenths.iter()) {
+                for ((e2, e3), e4) in ninths
+                    .iter()
+                    .cartesian_product(elevenths.iter())
+                    .cartesian_product(thirteenths.iter())
+                {
+                    let (t, e1, e2, e3, e4) = (t.clone(), *e1, *e2, *e3, *e4);
+                    if (e2, e3, e4) == (Interval::OMITTED, Interval::OMITTED, Interval::OMITTED) {
+                        continue;
+                    }
+                    /* altered tensions go with dominant chords, the
+                     * major seventh takes the #11 only
+                     */
+                    let is_altered = |e: &Interval| {
+                        [
+                            Interval::MINOR_9,
+                            Interval::PLUS_9,
+                            Interval::AUGMENTED_11,
+                            Interval::FLATTENED_13,
+                        ]
+                        .contains(e)
+                    };
+                    let allowed = match (&t, e1) {
+                        (TriadType::major, Interval::MINOR_7)
+                        | (TriadType::sus4, Interval::MINOR_7)
+                        | (TriadType::plus, Interval::MINOR_7) => true,
+                        (TriadType::major, _) => !is_altered(&e2) && !is_altered(&e4),
+                        /* minor sevenths take the b9 and b13 of the
+                         * phrygian and locrian modes, e.g. Em7b9
+                         */
+                        (TriadType::minor, Interval::MINOR_7)
+                        | (TriadType::minor_diminished, Interval::MINOR_7) => {
+                            ![Interval::PLUS_9, Interval::AUGMENTED_11]
+                                .iter()
+                                .any(|e| [e2, e3, e4].contains(e))
+                        }
+                        (TriadType::minor, _) => ![e2, e3, e4].iter().any(is_altered),
+                        _ => false,
+                    };
+                    if !allowed {
+                        continue;
+                    }
+                    types.push(match (e3, e4) {
+                        (Interval::OMITTED, Interval::OMITTED) => {
+                            ChordType::NineChord { t, e1, e2 }
+                        }
+                        (_, Interval::OMITTED) => ChordType::ElevenChord { t, e1, e2, e3 },
+                        _ => ChordType::ThirteenChord { t, e1, e2, e3, e4 },
+                    });
+                }
+            }
+            /* tones falling onto the same pitch class (the #9 of a minor
+             * chord) make no chord, a set already named keeps its name
+             */
+            types.sort_by_key(|type_| type_.intervals().len());
+            let mut templates: Vec<Template> = vec![];
+            for type_ in types {
+                let mask = type_.pitch_class_set();
+                if mask.count_ones() as usize != type_.intervals().len() + 1
+                    || templates.iter().any(|t| t.mask == mask)
+                {
+                    continue;
+                }
+                /* an added sixth or fourth without the fifth is rather
+                 * an inversion, e.g. C E A is Am/C
+                 */
+                let fifth_omissible = match type_ {
+                    ChordType::AddChord { e, .. } => e == Interval::MAJOR_9,
+                    _ => type_.without_fifth() != type_,
+                };
+                templates.push(Template {
+                    fifth_omissible,
+                    type_,
+                    mask,
+                });
+            }
+            templates
+        })
+    }
+    fn match_root(root: Note, pitch_classes: u16, max_missing: u32) -> Vec<TemplateMatch> {
+        /* All templates whose tones are played above the given root,
+         * allowing for a left out fifth and up to max_missing other
+         * tones, best fit first.
+         */
+        let shift = root.pitch_class().semitones as u32;
+        let set = ((pitch_classes >> shift) | (pitch_classes << (12 - shift))) & 0xfff;
+        let fifth = 1 << Interval::PERFECT_5.note.semitones;
+        let mut matches = Chord::templates()
             .iter()
-            .map(|s| Note { semitones: *s })
-            .collect::<Vec<_>>()
-    );
-    assert_eq!(
-        minor_inversions[1],
-        vec![7, 12, 15]
+            .enumerate()
+            .filter_map(|(idx, template)| {
+                let mut mask = template.mask;
+                let mut type_ = template.type_.clone();
+                if template.fifth_omissible && set & fifth == 0 {
+                    mask &= !fifth;
+                    type_ = type_.without_fifth();
+                }
+                let missing = mask & !set;
+                if missing.count_ones() > max_missing {
+                    return None;
+                }
+                Some(TemplateMatch {
+                    type_,
+                    rank: idx,
+                    omitted: (template.mask & !mask).count_ones() as usize,
+                    missing,
+                    extra: set & !mask,
+                })
+            })
+            .collect::<Vec<_>>();
+        /* two notes that are no third are named by their interval */
+        if set.count_ones() == 2 && matches.iter().all(|m| m.extra != 0 || m.missing != 0) {
+            matches.push(TemplateMatch {
+                type_: ChordType::TwoTone {
+                    t: Interval {
+                        note: Note {
+                            semitones: (set & !1).trailing_zeros() as i32,
+                        },
+                    },
+                },
+                rank: Chord::templates().len(),
+                /* it lacks a third, a triad without its fifth goes first */
+                omitted: 1,
+                missing: 0,
+                extra: 0,
+            });
+        }
+        matches.sort_by_key(|m| {
+            (
+                m.extra.count_ones() + m.missing.count_ones(),
+                m.omitted,
+                m.rank,
+            )
+        });
+        matches
+    }
+    pub fn pitch_class_set(notes: &[Note]) -> u16 {
+        notes
             .iter()
-            .map(|s| Note { semitones: *s })
-            .collect::<Vec<_>>()
-    );
-    assert_eq!(minor_inversions.len(), 2);
-    // now build from inversion
-    let intervals2 = [5, 0, 3]
-        .iter()
-        .map(|s| Note { semitones: *s })
-        .collect::<Vec<_>>();
-    let inversion2 = Chord::build_inversions(&intervals2);
-    assert_eq!(
-        inversion2[0],
-        vec![0, 3, 17]
+            .fold(0, |set, n| set | 1 << n.pitch_class().semitones)
+    }
+    fn roots(notes: &[Note]) -> Vec<Note> {
+        /* One note per pitch class, lowest first, as strings of
+         * re-entrant tunings don't ascend.
+         */
+        notes
             .iter()
-            .map(|s| Note { semitones: *s })
+            .copied()
+            .sorted()
+            .unique_by(|s| s.no_octaves())
             .collect::<Vec<_>>()
-    );
-    assert_eq!(
-        inversion2[1],
-        vec![3, 17, 12]
+    }
+    pub fn candidates(notes: &[Note]) -> Vec<ChordMatch> {
+        /* Every name the notes could go by, on any root, best first.
+         * Candidates are scored by the share of notes they explain,
+         * less for left out tones and for a root other than the bass.
+         */
+        let pitch_classes = Chord::pitch_class_set(notes);
+        let roots = Chord::roots(notes);
+        let mut candidates = vec![];
+        for (idx, root) in roots.iter().enumerate() {
+            for m in Chord::match_root(*root, pitch_classes, 1) {
+                let notes_of = |set: u16| {
+                    (0..12)
+                        .filter(|i| set & 1 << i != 0)
+                        .map(|i| (*root + Note { semitones: i }).pitch_class())
+                        .collect::<Vec<_>>()
+                };
+                let (extra, missing) = (notes_of(m.extra), notes_of(m.missing));
+                let explained = roots.len() - extra.len();
+                let mut score = (100 * explained / roots.len()) as i32
+                    - 10 * m.omitted as i32
+                    - 30 * missing.len() as i32;
+                if idx > 0 {
+                    score -= 5;
+                }
+                let chord = Chord::from_type(*root, m.type_, None).with_bass(roots[0]);
+                candidates.push((
+                    (-score, m.rank, idx),
+                    ChordMatch {
+                        chord,
+                        score,
+                        missing,
+                        extra,
+                    },
+                ));
+            }
+        }
+        candidates.sort_by_key(|(rank, _)| *rank);
+        candidates.into_iter().map(|(_, c)| c).collect()
+    }
+
+    pub fn find_chord(notes: &[Note], absent_roots: bool) -> Vec<Option<Chord>> {
+        /* The chord on each root, the lowest note first, or None where
+         * the notes are no complete chord on that root. With
+         * absent_roots, shell and rootless readings on roots that are
+         * not played follow.
+         */
+        let pitch_classes = Chord::pitch_class_set(notes);
+        let mut results = Chord::roots(notes)
+            .into_iter()
+            .map(|root| {
+                let best = Chord::match_root(root, pitch_classes, 0)
+                    .into_iter()
+                    .next()?;
+                match best.extra {
+                    0 => Some(Chord::from_type(root, best.type_, None)),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        if absent_roots {
+            results.extend(Chord::find_rootless(notes).into_iter().map(Some));
+        }
+        results
+    }
+    fn find_rootless(notes: &[Note]) -> Vec<Chord> {
+        /* A root below the voicing makes a seventh chord of it if the
+         * voicing holds that chord's third and seventh, the guide tones.
+         */
+        let bass = match notes.iter().min() {
+            Some(bass) => *bass,
+            None => return vec![],
+        };
+        let pitch_classes = Chord::pitch_class_set(notes);
+        let mut chords = vec![];
+        for pitch_class in 0..12 {
+            if pitch_classes & 1 << pitch_class != 0 {
+                continue;
+            }
+            let below_bass = (bass
+                - Note {
+                    semitones: pitch_class,
+                })
+            .pitch_class();
+            let root = bass - below_bass;
+            let best = match Chord::match_root(root, pitch_classes | 1 << pitch_class, 0)
+                .into_iter()
+                .next()
+            {
+                Some(best) if best.extra == 0 => best,
+                _ => continue,
+            };
+            let guide_tones = best
+                .type_
+                .intervals_and_degrees()
+                .into_iter()
+                .filter(|(i, degree)| match degree {
+                    3 => true,
+                    7 => i.note.semitones >= Interval::MINOR_7.note.semitones,
+                    _ => false,
+                })
+                .map(|(i, _)| (root + i.note).pitch_class())
+                .collect::<Vec<_>>();
+            if guide_tones.len() != 2
+                || guide_tones
+                    .iter()
+                    .any(|n| pitch_classes & 1 << n.semitones == 0)
+            {
+                continue;
+            }
+            /* a tritone away the same tones are altered, prefer the
+             * plain reading
+             */
+            let rank = (best.type_.alterations(), best.rank, below_bass);
+            let mut chord = Chord::from_type(root, best.type_, None);
+            chord.rootless = match pitch_classes.count_ones() {
+                2 => Some(RootlessVoicing::shell),
+                _ => Some(RootlessVoicing::rootless),
+            };
+            chords.push((rank, chord));
+        }
+        chords.sort_by_key(|(rank, _)| *rank);
+        chords.into_iter().map(|(_, c)| c).collect()
+    }
+    pub fn find_slash_chords(notes: &[Note]) -> Vec<Chord> {
+        /* Every name of the notes, best first, over their lowest
+         * sounding note, e.g. C/E. A bass that is no chord tone is put
+         * under the chord of the notes above it, e.g. Cmaj7/Ab.
+         */
+        let bass = match notes.iter().min() {
+            Some(bass) => *bass,
+            None => return vec![],
+        };
+        let exact = |notes: &[Note]| {
+            Chord::candidates(notes)
+                .into_iter()
+                .filter(|c| c.extra.is_empty() && c.missing.is_empty())
+                .map(|c| c.chord.with_bass(bass))
+                .collect::<Vec<_>>()
+        };
+        let chords = exact(notes);
+        if !chords.is_empty() {
+            return chords;
+        }
+        let upper = notes
             .iter()
-            .map(|s| Note { semitones: *s })
-            .collect::<Vec<_>>()
-    );
-    assert_eq!(inversion2.len(), 2);
+            .filter(|n| n.pitch_class() != bass.pitch_class())
+            .copied()
+            .collect::<Vec<_>>();
+        if Chord::roots(&upper).len() < 3 {
+            return vec![];
+        }
+        exact(&upper)
+    }
+}
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_string_with_root(&self.root_spelling()))
+    }
 }
 #[test]
 fn test_find_chord() {
@@ -530,17 +1326,17 @@ fn test_find_chord() {
         .iter()
         .map(|s| Note { semitones: *s + 11 })
         .collect::<Vec<_>>();
-    let chord1 = Chord::find_chord(&notes1);
-    let chord2 = Chord::find_chord(&notes2);
-    let chord3 = Chord::find_chord(&notes3);
-    let chord4 = Chord::find_chord(&notes4);
-    let chord5 = Chord::find_chord(&notes5);
-    let chord6 = Chord::find_chord(&notes6);
-    let chord7 = Chord::find_chord(&notes7);
-    let chord8 = Chord::find_chord(&notes8);
-    let chord9 = Chord::find_chord(&notes9);
-    let chord10 = Chord::find_chord(&notes10);
-    let chord11 = Chord::find_chord(&notes11);
+    let chord1 = Chord::find_chord(&notes1, false);
+    let chord2 = Chord::find_chord(&notes2, false);
+    let chord3 = Chord::find_chord(&notes3, false);
+    let chord4 = Chord::find_chord(&notes4, false);
+    let chord5 = Chord::find_chord(&notes5, false);
+    let chord6 = Chord::find_chord(&notes6, false);
+    let chord7 = Chord::find_chord(&notes7, false);
+    let chord8 = Chord::find_chord(&notes8, false);
+    let chord9 = Chord::find_chord(&notes9, false);
+    let chord10 = Chord::find_chord(&notes10, false);
+    let chord11 = Chord::find_chord(&notes11, false);
 
     assert_eq!(
         chord1[0].clone().unwrap().type_,
@@ -610,3 +1406,248 @@ fn test_find_chord() {
         }
     );
 }
+#[test]
+fn test_candidates() {
+    let notes = |semitones: Vec<i32>| {
+        semitones
+            .iter()
+            .map(|s| Note { semitones: *s })
+            .collect::<Vec<_>>()
+    };
+    let names = |candidates: Vec<ChordMatch>| {
+        candidates
+            .iter()
+            .take(3)
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+    };
+    // C E G B: the major seventh beats its parts
+    let candidates = Chord::candidates(&notes(vec![0, 4, 7, 11]));
+    assert_eq!(candidates[0].score, 100);
+    assert_eq!(names(candidates), vec!["Cmaj7", "C +B", "Em/C +C"]);
+    // E B E G C E: a major seventh over its third, not Em(b6)
+    let candidates = Chord::candidates(&notes(vec![4, 11, 16, 19, 24, 28]));
+    assert_eq!(candidates[0].to_string(), "Cmaj7/E");
+    // C E A: A minor over C beats C6 without its fifth
+    let candidates = Chord::candidates(&notes(vec![0, 4, 9]));
+    assert_eq!(candidates[0].chord.to_string(), "Am/C");
+    // C F# G: no chord, the closest lack a tone
+    let candidates = Chord::candidates(&notes(vec![0, 6, 7]));
+    assert_eq!(candidates[0].to_string(), "Cadd#11 -E");
+    // C E G Db D: no chord, the closest leaves one note out
+    let candidates = Chord::candidates(&notes(vec![0, 4, 7, 13, 14]));
+    assert_eq!(candidates[0].to_string(), "Cadd9 +Db");
+    assert_eq!(candidates[0].extra, notes(vec![1]));
+    assert!(Chord::find_chord(&notes(vec![0, 4, 7, 13, 14]), false)
+        .iter()
+        .all(|c| c.is_none()));
+    let candidates = Chord::candidates(&notes(vec![0, 4, 7, 11, 18]));
+    assert_eq!(candidates[0].chord.to_string(), "Cmaj7#11");
+    // C Bb D E without the fifth
+    let candidates = Chord::candidates(&notes(vec![0, 10, 14, 16]));
+    assert_eq!(candidates[0].chord.to_string(), "C9");
+    assert_eq!(candidates[0].score, 90);
+}
+#[test]
+fn test_slash_chords() {
+    let names = |semitones: Vec<i32>| {
+        let notes = semitones
+            .iter()
+            .map(|s| Note { semitones: *s })
+            .collect::<Vec<_>>();
+        Chord::find_slash_chords(&notes)
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(vec![4, 7, 12])[0], "C/E");
+    assert_eq!(names(vec![6, 9, 14, 18])[0], "D/F#");
+    assert_eq!(names(vec![7, 9, 12, 16]), vec!["Am7/G", "C6/G"]);
+    assert_eq!(names(vec![10, 12, 16, 19])[0], "C7/Bb");
+    assert_eq!(names(vec![6, 12, 16, 23])[2], "Cmaj7#11/F#");
+    // the bass is no chord tone
+    assert_eq!(names(vec![8, 12, 16, 19, 23])[0], "Cmaj7/Ab");
+    assert!(names(vec![0, 1, 2]).is_empty());
+}
+#[test]
+fn test_rootless() {
+    let names = |semitones: Vec<i32>| {
+        let notes = semitones
+            .iter()
+            .map(|s| Note { semitones: *s })
+            .collect::<Vec<_>>();
+        Chord::find_chord(&notes, true)
+            .iter()
+            .filter_map(|c| c.as_ref())
+            .filter(|c| c.get_rootless().is_some())
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+    };
+    // B F, the third and seventh of G7 and of Db7
+    assert_eq!(names(vec![11, 17]), vec!["G7 (shell)", "Db7 (shell)"]);
+    // F# A C E: 3-5-7-9 of D9
+    assert_eq!(names(vec![6, 9, 12, 16])[0], "D9 (rootless)");
+    // B E F A: 3-13-7-9 of G13 without its fifth
+    assert_eq!(names(vec![11, 16, 17, 21])[0], "G13 (rootless)");
+    let notes = vec![Note { semitones: 6 }, Note { semitones: 9 }];
+    assert!(Chord::find_chord(&notes, false).len() == 2);
+}
+#[test]
+fn test_from_symbol() {
+    let semitones = |c: &Chord| {
+        c.get_notes()
+            .iter()
+            .map(|n| n.semitones)
+            .collect::<Vec<_>>()
+    };
+
+    let chord = Chord::from_symbol("F#m7b5").unwrap();
+    assert_eq!(semitones(&chord), vec![6, 9, 12, 16]);
+    assert_eq!(chord.to_string(), "F#m7b5");
+
+    let chord = Chord::from_symbol("Bb13#11").unwrap();
+    assert_eq!(
+        chord.type_,
+        ChordType::ThirteenChord {
+            t: TriadType::major,
+            e1: Interval::MINOR_7,
+            e2: Interval::MAJOR_9,
+            e3: Interval::AUGMENTED_11,
+            e4: Interval::MAJOR_13
+        }
+    );
+    assert_eq!(semitones(&chord), vec![10, 14, 17, 20, 24, 28, 31]);
+
+    let chord = Chord::from_symbol("C/G").unwrap();
+    assert_eq!(chord.get_bass(), Some(Note { semitones: 7 }));
+    assert_eq!(chord.to_string(), "C/G");
+
+    let chord = Chord::from_symbol("Dsus4add9").unwrap();
+    assert_eq!(
+        chord.type_,
+        ChordType::AddChord {
+            t: TriadType::sus4,
+            e: Interval::MAJOR_9
+        }
+    );
+    assert_eq!(chord.interval_names(), vec!["1", "4", "5", "9"]);
+
+    assert_eq!(
+        Chord::from_symbol("Cdim7").unwrap().type_,
+        ChordType::SevenChord {
+            t: TriadType::minor_diminished,
+            e: Interval::MAJOR_6
+        }
+    );
+    assert_eq!(
+        Chord::from_symbol("AmMaj7").unwrap().type_,
+        ChordType::SevenChord {
+            t: TriadType::minor,
+            e: Interval::MAJOR_7
+        }
+    );
+    assert_eq!(
+        Chord::from_symbol("G6/9").unwrap().type_,
+        ChordType::NineChord {
+            t: TriadType::major,
+            e1: Interval::MAJOR_6,
+            e2: Interval::MAJOR_9
+        }
+    );
+    assert_eq!(
+        Chord::from_symbol("E5").unwrap().type_,
+        ChordType::TwoTone {
+            t: Interval::PERFECT_5
+        }
+    );
+    assert!(Chord::from_symbol("H7").is_none());
+    assert!(Chord::from_symbol("Cdim7").is_some());
+    assert!(Chord::from_symbol("Cfoo").is_none());
+    assert!(Chord::from_symbol("C/X").is_none());
+}
+#[test]
+fn test_spelling() {
+    let names = |symbol: &str| {
+        let chord = Chord::from_symbol(symbol).unwrap();
+        chord
+            .spelling(&chord.root_spelling())
+            .iter()
+            .map(|s| s.capitalized())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    assert_eq!(names("C#"), "Db F Ab");
+    assert_eq!(names("B"), "B D# F#");
+    assert_eq!(names("Ebm"), "Eb Gb Bb");
+    assert_eq!(names("Bbdim7"), "Bb Db Fb Abb");
+    assert_eq!(names("G#7#9"), "Ab C Eb Gb B");
+    let g_sharp = Spelling::from_string("g#").unwrap();
+    let spelled = Chord::from_symbol("G#7#9")
+        .unwrap()
+        .spelling(&g_sharp)
+        .iter()
+        .map(|s| s.capitalized())
+        .collect::<Vec<_>>();
+    assert_eq!(spelled.join(" "), "G# B# D# F# A##");
+    assert_eq!(names("D/F#"), "D F# A F#");
+    assert_eq!(Chord::from_symbol("A#").unwrap().to_string(), "Bb");
+    assert_eq!(Chord::from_symbol("Dbm").unwrap().to_string(), "C#m");
+    assert_eq!(Chord::from_symbol("C/A#").unwrap().to_string(), "C/Bb");
+}
+#[test]
+fn test_extended_chords() {
+    let name = |semitones: &[i32]| {
+        let notes = semitones
+            .iter()
+            .map(|s| Note { semitones: *s })
+            .collect::<Vec<_>>();
+        Chord::find_chord(&notes, false)[0]
+            .clone()
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(name(&[0, 4, 7, 10, 14, 21]), "C13");
+    assert_eq!(name(&[0, 4, 10, 14, 21]), "C13");
+    assert_eq!(name(&[0, 4, 10, 21]), "C7add13");
+    assert_eq!(name(&[0, 3, 7, 10, 13]), "Cm7b9");
+    assert_eq!(name(&[4, 7, 11, 14, 17]), "Em7b9");
+    assert_eq!(name(&[0, 3, 6, 10, 13]), "Cm7b5b9");
+    assert_eq!(name(&[0, 5, 7, 10]), "C7sus4");
+    assert_eq!(name(&[0, 2, 7, 10]), "C7sus2");
+    assert_eq!(name(&[0, 3, 6, 9]), "Cdim7");
+    assert_eq!(name(&[0, 3, 6, 10]), "Cm7b5");
+    assert_eq!(name(&[0, 4, 7, 10, 15]), "C7#9");
+    assert_eq!(name(&[0, 4, 7, 10, 13, 20]), "C7b9b13");
+    assert_eq!(name(&[0, 3, 7, 11]), "CmMaj7");
+    assert_eq!(name(&[0, 4, 7, 11]), "Cmaj7");
+    assert_eq!(name(&[0, 4, 7, 9, 14]), "C6/9");
+    assert_eq!(name(&[0, 2, 4, 7, 9]), "C6/9");
+    assert_eq!(name(&[0, 4, 7, 17]), "Cadd11");
+    assert_eq!(name(&[0, 3, 7, 17]), "Cmadd11");
+    assert_eq!(name(&[0, 3, 7, 10, 14, 17]), "Cm11");
+    assert_eq!(name(&[0, 4, 7, 11, 14, 18]), "Cmaj9#11");
+}
+#[test]
+fn test_symbol_names() {
+    /* symbols in standard notation are printed as they are read */
+    let symbols = [
+        "C13", "C13#11", "C7sus4", "C9sus4", "Cdim7", "Cm7b5", "C7#9", "C7b9b13", "CmMaj7",
+        "Cmaj7", "Cmaj9", "C6/9", "Cm6", "Cadd11", "Cmadd11", "C11", "Cm11", "C7add11", "C7#5",
+        "C(b5)", "Cm(b6)", "C+", "C6sus4", "C6sus2", "C6/9sus4", "C7add13", "Em7b9", "Cm7b5b9",
+    ];
+    for symbol in symbols.iter() {
+        assert_eq!(Chord::from_symbol(symbol).unwrap().to_string(), *symbol);
+    }
+    /* a sus chord with a sixth, named from its notes */
+    let g = Note { semitones: 7 };
+    let notes = [0, 5, 7, 9]
+        .iter()
+        .map(|i| g + Note { semitones: *i })
+        .collect::<Vec<_>>();
+    let chord = Chord::find_chord(&notes, false)[0].clone().unwrap();
+    assert_eq!(chord.to_string(), "G6sus4");
+    assert_eq!(
+        chord.with_bass(g + Note { semitones: 9 }).to_string(),
+        "G6sus4/E"
+    );
+}
@@ -0,0 +1,312 @@
+use super::chord::Chord;
+use super::transpose::Transposition;
+use std::cmp;
+
+/* ChordPro
+ * Songs as lyrics with the chords in brackets in front of the syllable
+ * they fall on, and directives in braces:
+ *
+ *     {title: Greensleeves}
+ *     {key: Am}
+ *     [Am]Alas, my [G]love, you [F]do me [E]wrong
+ *
+ * Chords that aren't symbols guitarnotes knows, e.g. [N.C.], are kept
+ * as they are written.
+ */
+#[derive(Clone)]
+pub struct SongChord {
+    pub symbol: String,
+    pub chord: Option<Chord>,
+}
+impl SongChord {
+    fn new(symbol: &str) -> SongChord {
+        SongChord {
+            symbol: symbol.to_string(),
+            chord: Chord::from_symbol(symbol),
+        }
+    }
+    fn renamed<F: Fn(&Chord) -> (Chord, String)>(&self, rename: &F) -> SongChord {
+        match &self.chord {
+            Some(chord) => {
+                let (chord, symbol) = rename(chord);
+                SongChord {
+                    symbol,
+                    chord: Some(chord),
+                }
+            }
+            None => self.clone(),
+        }
+    }
+}
+#[derive(Clone)]
+pub enum SongLine {
+    Directive {
+        name: String,
+        value: String,
+    },
+    /* chords with the position in the text (in characters) they fall on */
+    Lyrics {
+        chords: Vec<(usize, SongChord)>,
+        text: String,
+    },
+    Comment {
+        text: String,
+    },
+}
+pub struct Song {
+    pub lines: Vec<SongLine>,
+}
+impl Song {
+    /* short forms of the directives */
+    const ALIASES: [(&'static str, &'static str); 6] = [
+        ("t", "title"),
+        ("st", "subtitle"),
+        ("c", "comment"),
+        ("soc", "start_of_chorus"),
+        ("eoc", "end_of_chorus"),
+        ("ci", "comment_italic"),
+    ];
+
+    fn read_directive(line: &str) -> Option<SongLine> {
+        let inner = line.strip_prefix('{')?.strip_suffix('}')?;
+        let (name, value) = match inner.find([':', ' ']) {
+            Some(idx) => (&inner[..idx], inner[idx + 1..].trim()),
+            None => (inner, ""),
+        };
+        let name = name.trim().to_lowercase();
+        let name = match Song::ALIASES.iter().find(|(short, _)| *short == name) {
+            Some((_, long)) => long.to_string(),
+            None => name,
+        };
+        Some(SongLine::Directive {
+            name,
+            value: value.to_string(),
+        })
+    }
+    fn read_lyrics(line: &str) -> Option<SongLine> {
+        /* None if a bracket isn't closed */
+        let mut chords = vec![];
+        let mut text = String::new();
+        let mut rest = line;
+        while let Some(start) = rest.find('[') {
+            text.push_str(&rest[..start]);
+            let end = rest[start..].find(']')? + start;
+            chords.push((text.chars().count(), SongChord::new(&rest[start + 1..end])));
+            rest = &rest[end + 1..];
+        }
+        text.push_str(rest);
+        Some(SongLine::Lyrics {
+            chords,
+            text: text.trim_end().to_string(),
+        })
+    }
+    pub fn parse(text: &str) -> Option<Song> {
+        /* None if the text has neither chords nor directives */
+        let lines = text
+            .lines()
+            .map(|line| {
+                let trimmed = line.trim();
+                if let Some(comment) = trimmed.strip_prefix('#') {
+                    return Some(SongLine::Comment {
+                        text: comment.trim().to_string(),
+                    });
+                }
+                Song::read_directive(trimmed).or_else(|| Song::read_lyrics(line))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let song = Song { lines };
+        let has_directives = song
+            .lines
+            .iter()
+            .any(|l| matches!(l, SongLine::Directive { .. }));
+        if !has_directives && song.chords().is_empty() {
+            return None;
+        }
+        Some(song)
+    }
+
+    pub fn directive(&self, name: &str) -> Option<&str> {
+        /* the value of the first directive of that name */
+        self.lines.iter().find_map(|l| match l {
+            SongLine::Directive { name: n, value } if n == name => Some(&value[..]),
+            _ => None,
+        })
+    }
+    pub fn chords(&self) -> Vec<&SongChord> {
+        /* all chords in the order they are played */
+        self.lines
+            .iter()
+            .flat_map(|l| match l {
+                SongLine::Lyrics { chords, .. } => chords.iter().map(|(_, c)| c).collect(),
+                _ => vec![],
+            })
+            .collect()
+    }
+    fn with_chords<F: Fn(&Chord) -> (Chord, String)>(&self, rename: F) -> Song {
+        let lines = self
+            .lines
+            .iter()
+            .map(|l| match l {
+                SongLine::Lyrics { chords, text } => SongLine::Lyrics {
+                    chords: chords
+                        .iter()
+                        .map(|(pos, c)| (*pos, c.renamed(&rename)))
+                        .collect(),
+                    text: text.clone(),
+                },
+                _ => l.clone(),
+            })
+            .collect();
+        Song { lines }
+    }
+    fn set_directive(&mut self, name: &str, value: String) {
+        /* replaces the directive or adds it after the leading ones */
+        let new_line = SongLine::Directive {
+            name: name.to_string(),
+            value,
+        };
+        let existing = self
+            .lines
+            .iter()
+            .position(|l| matches!(l, SongLine::Directive { name: n, .. } if n == name));
+        let position = self
+            .lines
+            .iter()
+            .position(|l| !matches!(l, SongLine::Directive { .. }))
+            .unwrap_or(self.lines.len());
+        match existing {
+            Some(idx) => self.lines[idx] = new_line,
+            None => self.lines.insert(position, new_line),
+        }
+    }
+    pub fn transposed(&self, transposition: &Transposition) -> Song {
+        /* the chords, named in the new key, and the key directive */
+        let mut song =
+            self.with_chords(|chord| (transposition.chord(chord), transposition.chord_name(chord)));
+        if song.directive("key").is_some() {
+            song.set_directive("key", transposition.key_symbol());
+        }
+        song
+    }
+    pub fn for_capo(&self, capo: i32) -> Song {
+        /* The shapes to play with a capo on the given fret, the key
+         * stays in concert pitch.
+         */
+        let mut song = self.with_chords(|chord| {
+            let shape = chord.transposed(-capo);
+            let name = shape.to_string();
+            (shape, name)
+        });
+        song.set_directive("capo", capo.to_string());
+        song
+    }
+
+    pub fn to_chordpro(&self) -> String {
+        self.lines
+            .iter()
+            .map(|l| match l {
+                SongLine::Directive { name, value } if value.is_empty() => format!("{{{}}}", name),
+                SongLine::Directive { name, value } => format!("{{{}: {}}}", name, value),
+                SongLine::Comment { text } => format!("# {}", text),
+                SongLine::Lyrics { chords, text } => {
+                    let mut line = String::new();
+                    let mut chars = text.chars();
+                    let mut written = 0;
+                    for (pos, chord) in chords {
+                        line.extend(chars.by_ref().take(pos - written));
+                        written = *pos;
+                        line.push_str(&format!("[{}]", chord.symbol));
+                    }
+                    line.extend(chars);
+                    line
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+    pub fn render(&self) -> Vec<String> {
+        /* A plain text lead sheet with the chords on a line of their
+         * own above the lyrics. Chords that would run into each other
+         * are moved to the right.
+         */
+        let mut lines = vec![];
+        for line in &self.lines {
+            match line {
+                SongLine::Directive { name, value } => match &name[..] {
+                    "title" => lines.push(value.to_uppercase()),
+                    "subtitle" | "artist" => lines.push(value.clone()),
+                    "key" => lines.push(format!("Key: {}", value)),
+                    "capo" => lines.push(format!("Capo {}", value)),
+                    "comment" | "comment_italic" => lines.push(format!("({})", value)),
+                    "start_of_chorus" => lines.push(String::from("Chorus:")),
+                    _ => {}
+                },
+                SongLine::Comment { .. } => {}
+                SongLine::Lyrics { chords, text } => {
+                    let mut chord_line = String::new();
+                    for (pos, chord) in chords {
+                        let column = match chord_line.is_empty() {
+                            true => *pos,
+                            false => cmp::max(*pos, chord_line.chars().count() + 1),
+                        };
+                        let padding = column - chord_line.chars().count();
+                        chord_line.push_str(&" ".repeat(padding));
+                        chord_line.push_str(&chord.symbol);
+                    }
+                    if !chord_line.is_empty() {
+                        lines.push(chord_line);
+                    }
+                    if !text.is_empty() || chords.is_empty() {
+                        lines.push(text.clone());
+                    }
+                }
+            }
+        }
+        lines
+    }
+}
+#[test]
+fn test_parse() {
+    let song =
+        Song::parse("{t: Greensleeves}\n{key: Am}\n\n[Am]Alas, my [G]love\n# verse 2").unwrap();
+    assert_eq!(song.directive("title"), Some("Greensleeves"));
+    assert_eq!(song.lines.len(), 5);
+    let chords = song.chords();
+    assert_eq!(chords.len(), 2);
+    assert_eq!(chords[1].symbol, "G");
+    match &song.lines[3] {
+        SongLine::Lyrics { chords, text } => {
+            assert_eq!(text, "Alas, my love");
+            assert_eq!(chords[1].0, 9);
+        }
+        _ => panic!("not read as lyrics"),
+    }
+    assert!(Song::parse("just some words").is_none());
+    assert!(Song::parse("[Am unclosed").is_none());
+}
+#[test]
+fn test_transpose() {
+    use super::note::Note;
+    use super::scale::{Scale, ScaleType};
+    let song = Song::parse("{key: Am}\n[Am]Alas, my [G]love, [N.C.]you [E7/G#]do").unwrap();
+    let a_minor = Scale::from_type_and_root(Note { semitones: 9 }, ScaleType::minor);
+    let up = song.transposed(&Transposition::new(&a_minor, 1));
+    assert_eq!(
+        up.to_chordpro(),
+        "{key: Bbm}\n[Bbm]Alas, my [Ab]love, [N.C.]you [F7/A]do"
+    );
+    let capo = song
+        .transposed(&Transposition::new(&a_minor, 3))
+        .for_capo(3);
+    assert_eq!(capo.directive("key"), Some("Cm"));
+    assert_eq!(capo.directive("capo"), Some("3"));
+    assert_eq!(capo.chords()[0].symbol, "Am");
+    assert_eq!(
+        up.render(),
+        vec![
+            "Key: Bbm",
+            "Bbm      Ab    N.C. F7/A",
+            "Alas, my love, you do"
+        ]
+    );
+}
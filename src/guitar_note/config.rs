@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+
+/* User configuration
+ * A small ini style file with named sections, e.g.
+ *
+ *   [tunings]
+ *   nashville = E3 A3 D4 G4 B3 E4
+ *   cgcfce = C G C F C E
+ *
+ *   [scales]
+ *   spanish = 1 b2 3 4 5 b6 b7
+ *
+ * read from $GUITARNOTES_CONFIG or ~/.guitarnotes.
+ */
+pub struct Config {
+    sections: HashMap<String, Vec<(String, String)>>,
+}
+
+impl Config {
+    const PATH_VARIABLE: &'static str = "GUITARNOTES_CONFIG";
+    const FILE_NAME: &'static str = ".guitarnotes";
+
+    pub fn load() -> Config {
+        /* A missing or unreadable file is an empty configuration.
+         */
+        let path = match env::var(Config::PATH_VARIABLE) {
+            Ok(path) => Some(path),
+            Err(_) => env::var("HOME")
+                .ok()
+                .map(|home| format!("{}/{}", home, Config::FILE_NAME)),
+        };
+        match path.and_then(|p| fs::read_to_string(p).ok()) {
+            Some(text) => Config::from_str(&text),
+            None => Config {
+                sections: HashMap::new(),
+            },
+        }
+    }
+    pub fn from_str(text: &str) -> Config {
+        let mut sections: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        let mut section = String::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].trim().to_lowercase();
+            } else if let Some(pos) = line.find('=') {
+                let key = line[..pos].trim().to_lowercase();
+                let value = line[pos + 1..].trim().to_owned();
+                sections
+                    .entry(section.clone())
+                    .or_default()
+                    .push((key, value));
+            }
+        }
+        Config { sections }
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        /* Later entries override earlier ones of the same name.
+         */
+        let key = key.to_lowercase();
+        self.sections
+            .get(section)?
+            .iter()
+            .rev()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| &v[..])
+    }
+    pub fn entries(&self, section: &str) -> Vec<(String, String)> {
+        match self.sections.get(section) {
+            Some(entries) => entries.clone(),
+            None => vec![],
+        }
+    }
+}
+#[test]
+fn test_config() {
+    let config = Config::from_str(
+        "# my tunings
+        [Tunings]
+        nashville = E3 A3 D4 G4 B3 E4
+        CGCFCE = C G C F C E
+        nashville = E3 A3 D4 G4 B3 E5
+
+        [scales]
+        spanish = 1 b2 3 4 5 b6 b7",
+    );
+    assert_eq!(config.get("tunings", "cgcfce"), Some("C G C F C E"));
+    assert_eq!(
+        config.get("tunings", "Nashville"),
+        Some("E3 A3 D4 G4 B3 E5")
+    );
+    assert_eq!(config.get("tunings", "spanish"), None);
+    assert_eq!(config.entries("scales").len(), 1);
+    assert_eq!(config.entries("chords").len(), 0);
+}
@@ -0,0 +1,199 @@
+use std::cmp;
+
+/* Chord boxes
+ * Vertical chord diagrams as found on chord sheets: strings run top to
+ * bottom with the lowest string on the left, frets left to right.
+ */
+struct Glyphs {
+    /* left corner, fill, string crossing, right corner */
+    nut: [&'static str; 4],
+    top: [&'static str; 4],
+    fret: [&'static str; 4],
+    string: &'static str,
+    dot: &'static str,
+    muted: &'static str,
+    open: &'static str,
+}
+
+pub struct ChordBox {
+    frets: Vec<Option<i32>>,
+    string_names: Vec<String>,
+}
+
+impl ChordBox {
+    const MIN_FRETS_SHOWN: i32 = 4;
+    const LABEL_WIDTH: usize = 5;
+    const ASCII: Glyphs = Glyphs {
+        nut: ["+", "=", "+", "+"],
+        top: ["+", "-", "+", "+"],
+        fret: ["+", "-", "+", "+"],
+        string: "|",
+        dot: "O",
+        muted: "x",
+        open: "o",
+    };
+    const UNICODE: Glyphs = Glyphs {
+        nut: ["╒", "═", "╤", "╕"],
+        top: ["┌", "─", "┬", "┐"],
+        fret: ["├", "─", "┼", "┤"],
+        string: "│",
+        dot: "●",
+        muted: "×",
+        open: "○",
+    };
+
+    pub fn new(frets: Vec<Option<i32>>, string_names: Vec<String>) -> ChordBox {
+        /* One fret per string, None for muted strings.
+         */
+        ChordBox {
+            frets,
+            string_names,
+        }
+    }
+
+    fn fretted(&self) -> Vec<i32> {
+        self.frets
+            .iter()
+            .filter_map(|f| *f)
+            .filter(|f| *f > 0)
+            .collect()
+    }
+    pub fn first_fret(&self) -> i32 {
+        /* Start at the nut if the shape fits into the first frets,
+         * otherwise at the lowest fretted note.
+         */
+        let fretted = self.fretted();
+        match fretted.iter().max() {
+            Some(highest) if *highest > ChordBox::MIN_FRETS_SHOWN => *fretted.iter().min().unwrap(),
+            _ => 1,
+        }
+    }
+    fn frets_shown(&self) -> i32 {
+        let highest = *self.fretted().iter().max().unwrap_or(&0);
+        cmp::max(ChordBox::MIN_FRETS_SHOWN, highest - self.first_fret() + 1)
+    }
+
+    fn line(&self, glyphs: [&'static str; 4]) -> String {
+        let [left, fill, crossing, right] = glyphs;
+        let inner = vec![fill.repeat(2); self.frets.len() - 1].join(crossing);
+        format!("{}{}{}", left, inner, right)
+    }
+
+    pub fn render(&self, unicode: bool) -> String {
+        let glyphs = match unicode {
+            true => &ChordBox::UNICODE,
+            false => &ChordBox::ASCII,
+        };
+        let first_fret = self.first_fret();
+        let label_width = ChordBox::LABEL_WIDTH;
+        let blank = " ".repeat(label_width);
+        let mut lines = vec![];
+
+        let markers = self
+            .frets
+            .iter()
+            .map(|f| match f {
+                None => glyphs.muted,
+                Some(0) => glyphs.open,
+                Some(_) => " ",
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        lines.push(format!("{}{}", blank, markers.trim_end()));
+        lines.push(match first_fret {
+            1 => format!("{}{}", blank, self.line(glyphs.nut)),
+            _ => format!("{}{}", blank, self.line(glyphs.top)),
+        });
+        for fret in first_fret..first_fret + self.frets_shown() {
+            let label = match fret == first_fret && first_fret > 1 {
+                true => format!("{:>w$} ", format!("{}fr", fret), w = label_width - 1),
+                false => blank.clone(),
+            };
+            let strings = self
+                .frets
+                .iter()
+                .map(|f| match f {
+                    Some(f) if *f == fret => glyphs.dot,
+                    _ => glyphs.string,
+                })
+                .collect::<Vec<_>>()
+                .join("  ");
+            lines.push(format!("{}{}", label, strings));
+            lines.push(format!("{}{}", blank, self.line(glyphs.fret)));
+        }
+        let names = self
+            .string_names
+            .iter()
+            .map(|n| format!("{:<3}", n.to_uppercase()))
+            .collect::<String>();
+        lines.push(format!("{}{}", blank, names.trim_end()));
+        lines.join("\n")
+    }
+    pub fn side_by_side(boxes: &[(String, ChordBox)], unicode: bool) -> String {
+        /* Boxes in a row with the chord names above them, as at the
+         * end of a lead sheet.
+         */
+        let rendered = boxes
+            .iter()
+            .map(|(name, chord_box)| {
+                let mut lines = vec![format!("{}{}", " ".repeat(ChordBox::LABEL_WIDTH), name)];
+                lines.extend(chord_box.render(unicode).lines().map(String::from));
+                lines
+            })
+            .collect::<Vec<_>>();
+        let height = rendered.iter().map(|lines| lines.len()).max().unwrap_or(0);
+        (0..height)
+            .map(|row| {
+                rendered
+                    .iter()
+                    .map(|lines| {
+                        let width = lines.iter().map(|l| l.chars().count()).max().unwrap();
+                        let line = lines.get(row).map(|l| &l[..]).unwrap_or("");
+                        format!("{:<w$}", line, w = width)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+#[test]
+fn test_render_chord_box() {
+    let names = ["e", "a", "d", "g", "b", "e"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+    let c_major = ChordBox::new(
+        vec![None, Some(3), Some(2), Some(0), Some(1), Some(0)],
+        names.clone(),
+    );
+    assert_eq!(c_major.first_fret(), 1);
+    let expected = [
+        "     x        o     o",
+        "     +==+==+==+==+==+",
+        "     |  |  |  |  O  |",
+        "     +--+--+--+--+--+",
+        "     |  |  O  |  |  |",
+        "     +--+--+--+--+--+",
+        "     |  O  |  |  |  |",
+        "     +--+--+--+--+--+",
+        "     |  |  |  |  |  |",
+        "     +--+--+--+--+--+",
+        "     E  A  D  G  B  E",
+    ]
+    .join("\n");
+    assert_eq!(c_major.render(false), expected);
+
+    let a_barre = ChordBox::new(
+        vec![Some(5), Some(7), Some(7), Some(6), Some(5), Some(5)],
+        names,
+    );
+    assert_eq!(a_barre.first_fret(), 5);
+    let rendered = a_barre.render(true);
+    let lines = rendered.lines().collect::<Vec<_>>();
+    assert_eq!(lines[1], "     ┌──┬──┬──┬──┬──┐");
+    assert_eq!(lines[2], " 5fr ●  │  │  │  ●  ●");
+}
@@ -0,0 +1,79 @@
+use std::cmp;
+use std::ops::RangeInclusive;
+
+/* Fret windows
+ * The part of the neck the fretboard printer shows. Frets are counted
+ * from the nut (or the capo), 0 being the open string.
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FretWindow {
+    first: i32,
+    last: i32,
+}
+
+impl FretWindow {
+    pub const DEFAULT_NECK: i32 = 24;
+
+    pub fn new(neck: i32) -> FretWindow {
+        /* The whole neck, from the nut to the highest fret.
+         */
+        FretWindow {
+            first: 0,
+            last: neck,
+        }
+    }
+    pub fn from_string(spec: &str, neck: i32) -> Option<FretWindow> {
+        /* Parses a window such as 5-9, or 7 for a single fret.
+         */
+        let mut bounds = spec.splitn(2, '-').map(|s| s.trim().parse::<i32>());
+        let first = bounds.next()?.ok()?;
+        let last = match bounds.next() {
+            Some(last) => last.ok()?,
+            None => first,
+        };
+        if first < 0 || last < first || last > neck {
+            return None;
+        }
+        Some(FretWindow { first, last })
+    }
+
+    pub fn shows_nut(&self) -> bool {
+        self.first == 0
+    }
+    pub fn frets(&self) -> RangeInclusive<i32> {
+        self.first..=self.last
+    }
+    pub fn position(&self) -> FretWindow {
+        /* The first five frets of the window, what the hand reaches
+         * without shifting.
+         */
+        FretWindow {
+            first: self.first,
+            last: cmp::min(self.last, self.first + 4),
+        }
+    }
+    pub fn fretted(&self) -> RangeInclusive<i32> {
+        /* The frets of the window without the open strings.
+         */
+        cmp::max(self.first, 1)..=self.last
+    }
+}
+#[test]
+fn test_fret_window() {
+    let window = FretWindow::from_string("5-9", 24).unwrap();
+    assert!(!window.shows_nut());
+    assert_eq!(window.fretted().collect::<Vec<_>>(), vec![5, 6, 7, 8, 9]);
+    assert_eq!(FretWindow::from_string("7", 24).unwrap().frets().count(), 1);
+    assert_eq!(
+        FretWindow::from_string("0-3", 24)
+            .unwrap()
+            .fretted()
+            .count(),
+        3
+    );
+    assert_eq!(FretWindow::from_string("12-20", 19), None);
+    assert_eq!(FretWindow::from_string("9-5", 24), None);
+    assert_eq!(FretWindow::from_string("a-5", 24), None);
+    assert_eq!(FretWindow::new(19).fretted().count(), 19);
+    assert_eq!(FretWindow::new(24).position().frets(), 0..=4);
+}
@@ -0,0 +1,226 @@
+use super::chord::Chord;
+use super::note::Note;
+use super::scale::{Scale, ScaleType};
+use std::fmt;
+
+/* Key detection
+ * Every major and minor key and church mode is rated by how many
+ * chords of a progression it explains, and by whether the progression
+ * starts and ends on its tonic.
+ */
+pub enum ChordFunction {
+    Diatonic,
+    /* the major fifth of a minor key, taken from harmonic minor */
+    Dominant,
+    SecondaryDominant { target: String },
+    Borrowed { from: String },
+    NonDiatonic,
+}
+
+impl ChordFunction {
+    fn weight(&self) -> f64 {
+        match self {
+            ChordFunction::Diatonic | ChordFunction::Dominant => 1.0,
+            ChordFunction::SecondaryDominant { .. } | ChordFunction::Borrowed { .. } => 0.5,
+            ChordFunction::NonDiatonic => 0.0,
+        }
+    }
+    pub fn is_diatonic(&self) -> bool {
+        matches!(self, ChordFunction::Diatonic | ChordFunction::Dominant)
+    }
+}
+impl fmt::Display for ChordFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChordFunction::Diatonic => write!(f, "diatonic"),
+            ChordFunction::Dominant => write!(f, "dominant from harmonic minor"),
+            ChordFunction::SecondaryDominant { target } => {
+                write!(f, "secondary dominant of {}", target)
+            }
+            ChordFunction::Borrowed { from } => write!(f, "borrowed from {}", from),
+            ChordFunction::NonDiatonic => write!(f, "non-diatonic"),
+        }
+    }
+}
+
+pub struct KeyMatch {
+    pub scale: Scale,
+    /* between 0 and 1 */
+    pub confidence: f64,
+    /* one per chord of the progression */
+    pub functions: Vec<ChordFunction>,
+}
+
+impl KeyMatch {
+    const KEY_TYPES: [ScaleType; 7] = [
+        ScaleType::major,
+        ScaleType::minor,
+        ScaleType::dorian,
+        ScaleType::mixolydian,
+        ScaleType::phrygian,
+        ScaleType::lydian,
+        ScaleType::locrian,
+    ];
+    /* modes need their tonic to beat the relative major or minor */
+    const MODE_PENALTY: f64 = 0.25;
+    const TONIC_BONUS: f64 = 1.0;
+
+    fn pitch_classes(notes: &[Note]) -> Vec<Note> {
+        notes.iter().map(|n| n.pitch_class()).collect()
+    }
+    fn chord_pitch_classes(chord: &Chord) -> Vec<Note> {
+        let mut notes = KeyMatch::pitch_classes(chord.get_notes());
+        notes.extend(chord.get_bass().map(|b| b.pitch_class()));
+        notes
+    }
+    fn chord_root(chord: &Chord) -> Note {
+        chord.get_notes()[0].pitch_class()
+    }
+    fn fits(chord: &Chord, scale: &Scale) -> bool {
+        let scale_notes = KeyMatch::pitch_classes(scale.get_notes());
+        KeyMatch::chord_pitch_classes(chord)
+            .iter()
+            .all(|n| scale_notes.contains(n))
+    }
+    pub fn scale_name(scale: &Scale) -> String {
+        format!(
+            "{} {:?}",
+            scale.spelling()[0].capitalized(),
+            scale.scale_type
+        )
+    }
+    pub fn chord_function(chord: &Chord, scale: &Scale) -> ChordFunction {
+        if KeyMatch::fits(chord, scale) {
+            return ChordFunction::Diatonic;
+        }
+        let tonic = scale.get_notes()[0].pitch_class();
+        let root = KeyMatch::chord_root(chord);
+        if let ScaleType::minor = scale.scale_type {
+            if (root - tonic).pitch_class().semitones == 7 && chord.is_dominant() {
+                return ChordFunction::Dominant;
+            }
+        }
+        /* a dominant resolving a fifth down onto a major or minor
+         * chord of the key
+         */
+        let scale_notes = KeyMatch::pitch_classes(scale.get_notes());
+        let target_root = (root + Note { semitones: 5 }).pitch_class();
+        if let Some(degree) = scale_notes.iter().position(|n| *n == target_root) {
+            let target = &scale.chords_in_scale()[degree];
+            if chord.is_dominant() && degree != 0 && !target.ends_with("dim") {
+                return ChordFunction::SecondaryDominant {
+                    target: target.clone(),
+                };
+            }
+        }
+        /* major and minor keys borrow from each other, the modes are
+         * what they borrow from
+         */
+        let parallel_type = match scale.scale_type {
+            ScaleType::major => ScaleType::minor,
+            ScaleType::minor => ScaleType::major,
+            _ => return ChordFunction::NonDiatonic,
+        };
+        let parallel = Scale::from_type_and_root(tonic, parallel_type);
+        if KeyMatch::fits(chord, &parallel) {
+            return ChordFunction::Borrowed {
+                from: KeyMatch::scale_name(&parallel),
+            };
+        }
+        ChordFunction::NonDiatonic
+    }
+    pub fn detect(chords: &[Chord]) -> Vec<KeyMatch> {
+        /* Confidence is the share of the best possible score: every
+         * chord diatonic and the progression starting and ending on
+         * the tonic chord.
+         */
+        if chords.is_empty() {
+            return vec![];
+        }
+        let max_score = chords.len() as f64 + 2.0 * KeyMatch::TONIC_BONUS;
+        let mut matches = vec![];
+        for (type_idx, scale_type) in KeyMatch::KEY_TYPES.iter().enumerate() {
+            for root in 0..12 {
+                let scale = Scale::from_type_and_root(Note { semitones: root }, scale_type.clone());
+                let functions = chords
+                    .iter()
+                    .map(|c| KeyMatch::chord_function(c, &scale))
+                    .collect::<Vec<_>>();
+                let is_tonic = |idx: usize| {
+                    KeyMatch::chord_root(&chords[idx]).semitones == root
+                        && functions[idx].is_diatonic()
+                };
+                let mut score = functions.iter().map(|f| f.weight()).sum::<f64>();
+                if is_tonic(0) {
+                    score += KeyMatch::TONIC_BONUS;
+                }
+                if is_tonic(chords.len() - 1) {
+                    score += KeyMatch::TONIC_BONUS;
+                }
+                if type_idx > 1 {
+                    score -= KeyMatch::MODE_PENALTY;
+                }
+                let confidence = (score / max_score).max(0.0);
+                matches.push((
+                    (type_idx, root),
+                    KeyMatch {
+                        scale,
+                        confidence,
+                        functions,
+                    },
+                ));
+            }
+        }
+        matches.sort_by(|(r1, m1), (r2, m2)| {
+            m2.confidence
+                .partial_cmp(&m1.confidence)
+                .unwrap()
+                .then(r1.cmp(r2))
+        });
+        matches.into_iter().map(|(_, m)| m).collect()
+    }
+    pub fn name(&self) -> String {
+        KeyMatch::scale_name(&self.scale)
+    }
+}
+#[test]
+fn test_detect() {
+    let chords = ["C", "Am", "F", "G7", "C"]
+        .iter()
+        .map(|s| Chord::from_symbol(s).unwrap())
+        .collect::<Vec<_>>();
+    let keys = KeyMatch::detect(&chords);
+    assert_eq!(keys[0].name(), "C major");
+    assert_eq!(keys[0].confidence, 1.0);
+    assert!(keys[0].functions.iter().all(|f| f.is_diatonic()));
+
+    let chords = ["Am", "Dm", "E7", "Am"]
+        .iter()
+        .map(|s| Chord::from_symbol(s).unwrap())
+        .collect::<Vec<_>>();
+    let keys = KeyMatch::detect(&chords);
+    assert_eq!(keys[0].name(), "A minor");
+    assert_eq!(
+        keys[0].functions[2].to_string(),
+        "dominant from harmonic minor"
+    );
+
+    let chords = ["G", "F", "C", "G"]
+        .iter()
+        .map(|s| Chord::from_symbol(s).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(KeyMatch::detect(&chords)[0].name(), "G mixolydian");
+}
+#[test]
+fn test_chord_function() {
+    let c_major = Scale::from_type_and_root(Note { semitones: 0 }, ScaleType::major);
+    let function = |symbol: &str| {
+        KeyMatch::chord_function(&Chord::from_symbol(symbol).unwrap(), &c_major).to_string()
+    };
+    assert_eq!(function("Dm7"), "diatonic");
+    assert_eq!(function("E7"), "secondary dominant of Am");
+    assert_eq!(function("D"), "secondary dominant of G");
+    assert_eq!(function("Bb"), "borrowed from C minor");
+    assert_eq!(function("Fm"), "borrowed from C minor");
+    assert_eq!(function("F#"), "non-diatonic");
+}
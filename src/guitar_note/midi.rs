@@ -0,0 +1,469 @@
+use super::pitch::Pitch;
+use std::cmp;
+
+/* Standard MIDI files
+ * Notes are kept with their start and length in ticks and written as a
+ * type 0 file (one track) or a type 1 file (a tempo track followed by
+ * the notes), all on the first channel. Files that are read have the
+ * notes of all their tracks but the drum channel merged, and their
+ * ticks converted to ours.
+ */
+#[derive(Clone, Copy)]
+pub struct MidiOptions {
+    /* quarter notes per minute */
+    pub tempo: u32,
+    pub velocity: u8,
+    /* delay between the strings of a strummed chord */
+    pub strum_ms: u32,
+    /* General MIDI program, counted from 0 (25 is the steel string guitar) */
+    pub program: u8,
+    /* SMF type 0 or 1 */
+    pub format: u16,
+}
+impl Default for MidiOptions {
+    fn default() -> MidiOptions {
+        MidiOptions {
+            tempo: 100,
+            velocity: 96,
+            strum_ms: 15,
+            program: 25,
+            format: 1,
+        }
+    }
+}
+pub struct MidiNote {
+    pub pitch: Pitch,
+    /* in ticks */
+    pub start: u32,
+    pub length: u32,
+    pub velocity: u8,
+}
+pub struct MidiFile {
+    pub options: MidiOptions,
+    pub notes: Vec<MidiNote>,
+    /* where the next run or strum starts */
+    cursor: u32,
+}
+
+impl MidiFile {
+    pub const TICKS_PER_QUARTER: u32 = 480;
+    const NOTE_OFF: u8 = 0x80;
+    const NOTE_ON: u8 = 0x90;
+    const PROGRAM_CHANGE: u8 = 0xc0;
+    const META: u8 = 0xff;
+    const META_TRACK_NAME: u8 = 0x03;
+    const META_TEMPO: u8 = 0x51;
+    const META_END_OF_TRACK: u8 = 0x2f;
+    const SYSEX: u8 = 0xf0;
+    const DRUM_CHANNEL: u8 = 9;
+    /* onsets closer than a sixteenth note are one strum */
+    const STRUM_TICKS: u32 = MidiFile::TICKS_PER_QUARTER / 4;
+    /* bars of four quarter notes */
+    const TICKS_PER_BAR: u32 = 4 * MidiFile::TICKS_PER_QUARTER;
+
+    pub fn new(options: &MidiOptions) -> MidiFile {
+        MidiFile {
+            options: *options,
+            notes: vec![],
+            cursor: 0,
+        }
+    }
+    fn ticks(beats: f64) -> u32 {
+        (beats * MidiFile::TICKS_PER_QUARTER as f64).round() as u32
+    }
+    pub fn add_run(&mut self, pitches: &Vec<Pitch>, beats_per_note: f64) {
+        /* one note after the other, e.g. a scale */
+        let length = MidiFile::ticks(beats_per_note);
+        for pitch in pitches {
+            self.notes.push(MidiNote {
+                pitch: *pitch,
+                start: self.cursor,
+                length,
+                velocity: self.options.velocity,
+            });
+            self.cursor += length;
+        }
+    }
+    pub fn add_strum(&mut self, pitches: &[Pitch], beats: f64) {
+        /* A down stroke: the strings, lowest first, each a little
+         * later than the one before, ring until the next chord. No
+         * pitches make a rest.
+         */
+        let length = MidiFile::ticks(beats);
+        let delay = MidiFile::ticks(
+            self.options.strum_ms as f64 / 1000.0 * self.options.tempo as f64 / 60.0,
+        );
+        for (idx, pitch) in pitches.iter().enumerate() {
+            let offset = cmp::min(idx as u32 * delay, length - 1);
+            self.notes.push(MidiNote {
+                pitch: *pitch,
+                start: self.cursor + offset,
+                length: length - offset,
+                velocity: self.options.velocity,
+            });
+        }
+        self.cursor += length;
+    }
+
+    fn variable_length(value: u32) -> Vec<u8> {
+        /* seven bits per byte, most significant first, the high bit
+         * set on all but the last
+         */
+        let mut bytes = vec![(value & 0x7f) as u8];
+        let mut rest = value >> 7;
+        while rest > 0 {
+            bytes.insert(0, (rest & 0x7f) as u8 | 0x80);
+            rest >>= 7;
+        }
+        bytes
+    }
+    fn chunk(name: &[u8; 4], data: Vec<u8>) -> Vec<u8> {
+        let mut bytes = name.to_vec();
+        bytes.extend(&(data.len() as u32).to_be_bytes());
+        bytes.extend(data);
+        bytes
+    }
+    fn meta(kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![MidiFile::META, kind];
+        bytes.extend(MidiFile::variable_length(data.len() as u32));
+        bytes.extend(data);
+        bytes
+    }
+    fn tempo_events(&self) -> Vec<(u32, Vec<u8>)> {
+        let micros_per_quarter = 60_000_000 / self.options.tempo;
+        vec![(
+            0,
+            MidiFile::meta(MidiFile::META_TEMPO, &micros_per_quarter.to_be_bytes()[1..]),
+        )]
+    }
+    fn note_events(&self) -> Vec<(u32, Vec<u8>)> {
+        let mut events = vec![
+            (0, MidiFile::meta(MidiFile::META_TRACK_NAME, b"guitarnotes")),
+            (0, vec![MidiFile::PROGRAM_CHANGE, self.options.program]),
+        ];
+        /* at the same tick notes end before others begin */
+        let mut notes = vec![];
+        for note in &self.notes {
+            let key = note.pitch.midi as u8;
+            notes.push((note.start, 1, vec![MidiFile::NOTE_ON, key, note.velocity]));
+            notes.push((
+                note.start + note.length,
+                0,
+                vec![MidiFile::NOTE_OFF, key, 64],
+            ));
+        }
+        notes.sort_by_key(|(tick, order, _)| (*tick, *order));
+        events.extend(notes.into_iter().map(|(tick, _, event)| (tick, event)));
+        events
+    }
+    fn track(events: Vec<(u32, Vec<u8>)>) -> Vec<u8> {
+        /* events in time order, written with the ticks since the last */
+        let mut data = vec![];
+        let mut last = 0;
+        for (tick, event) in &events {
+            data.extend(MidiFile::variable_length(tick - last));
+            data.extend(event);
+            last = *tick;
+        }
+        data.extend(MidiFile::variable_length(0));
+        data.extend(MidiFile::meta(MidiFile::META_END_OF_TRACK, &[]));
+        MidiFile::chunk(b"MTrk", data)
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let tracks = match self.options.format {
+            0 => {
+                let mut events = self.tempo_events();
+                events.extend(self.note_events());
+                vec![MidiFile::track(events)]
+            }
+            _ => vec![
+                MidiFile::track(self.tempo_events()),
+                MidiFile::track(self.note_events()),
+            ],
+        };
+        let mut header = vec![];
+        header.extend(&self.options.format.to_be_bytes());
+        header.extend(&(tracks.len() as u16).to_be_bytes());
+        header.extend(&(MidiFile::TICKS_PER_QUARTER as u16).to_be_bytes());
+        let mut bytes = MidiFile::chunk(b"MThd", header);
+        bytes.extend(tracks.concat());
+        bytes
+    }
+
+    fn read_variable_length(data: &[u8], pos: &mut usize) -> Option<u32> {
+        let mut value = 0;
+        loop {
+            let byte = *data.get(*pos)?;
+            *pos += 1;
+            value = (value << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    }
+    fn read_track(&mut self, data: &[u8], division: u32) -> Option<()> {
+        /* Channel messages may leave out their status byte if it is
+         * the one before (running status). A note on with velocity 0
+         * is a note off.
+         */
+        let to_ticks =
+            |tick: u32| (tick as u64 * MidiFile::TICKS_PER_QUARTER as u64 / division as u64) as u32;
+        let mut pos = 0;
+        let mut tick = 0;
+        let mut status = 0;
+        /* channel, key, start and velocity of the sounding notes */
+        let mut sounding: Vec<(u8, u8, u32, u8)> = vec![];
+        while pos < data.len() {
+            tick += MidiFile::read_variable_length(data, &mut pos)?;
+            if *data.get(pos)? & 0x80 != 0 {
+                status = data[pos];
+                pos += 1;
+            }
+            let channel = status & 0x0f;
+            match status & 0xf0 {
+                MidiFile::NOTE_OFF | MidiFile::NOTE_ON => {
+                    let (key, velocity) = (*data.get(pos)?, *data.get(pos + 1)?);
+                    pos += 2;
+                    if channel == MidiFile::DRUM_CHANNEL {
+                        continue;
+                    }
+                    if status & 0xf0 == MidiFile::NOTE_ON && velocity > 0 {
+                        sounding.push((channel, key, tick, velocity));
+                        continue;
+                    }
+                    if let Some(idx) = sounding.iter().position(|n| n.0 == channel && n.1 == key) {
+                        let (_, key, start, velocity) = sounding.remove(idx);
+                        self.notes.push(MidiNote {
+                            pitch: Pitch { midi: key as i32 },
+                            start: to_ticks(start),
+                            length: to_ticks(tick) - to_ticks(start),
+                            velocity,
+                        });
+                    }
+                }
+                MidiFile::PROGRAM_CHANGE => {
+                    if channel != MidiFile::DRUM_CHANNEL {
+                        self.options.program = *data.get(pos)?;
+                    }
+                    pos += 1;
+                }
+                /* channel pressure */
+                0xd0 => pos += 1,
+                MidiFile::SYSEX => {
+                    if status == MidiFile::META {
+                        let kind = *data.get(pos)?;
+                        pos += 1;
+                        let length = MidiFile::read_variable_length(data, &mut pos)? as usize;
+                        let meta = data.get(pos..pos + length)?;
+                        if kind == MidiFile::META_TEMPO && length == 3 {
+                            let micros = u32::from_be_bytes([0, meta[0], meta[1], meta[2]]);
+                            self.options.tempo = 60_000_000 / cmp::max(micros, 1);
+                        }
+                        pos += length;
+                    } else {
+                        let length = MidiFile::read_variable_length(data, &mut pos)? as usize;
+                        pos += length;
+                    }
+                    status = 0;
+                }
+                /* key pressure, controllers and pitch bend */
+                _ => pos += 2,
+            }
+        }
+        /* notes that are never switched off end with the track */
+        for (_, key, start, velocity) in sounding {
+            self.notes.push(MidiNote {
+                pitch: Pitch { midi: key as i32 },
+                start: to_ticks(start),
+                length: to_ticks(tick) - to_ticks(start),
+                velocity,
+            });
+        }
+        Some(())
+    }
+    pub fn parse(bytes: &[u8]) -> Option<MidiFile> {
+        /* None for anything but a Standard MIDI File with its time
+         * in ticks per quarter note
+         */
+        let chunk_length = |pos: usize| -> Option<usize> {
+            let length = bytes.get(pos + 4..pos + 8)?;
+            Some(u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize)
+        };
+        if bytes.get(..4)? != b"MThd" {
+            return None;
+        }
+        let header = bytes.get(8..8 + chunk_length(0)?)?;
+        let division = u16::from_be_bytes([*header.get(4)?, *header.get(5)?]) as u32;
+        if division & 0x8000 != 0 || division == 0 {
+            return None;
+        }
+        let mut file = MidiFile::new(&MidiOptions {
+            format: u16::from_be_bytes([header[0], header[1]]),
+            ..Default::default()
+        });
+        let mut pos = 8 + header.len();
+        while pos + 8 <= bytes.len() {
+            let length = chunk_length(pos)?;
+            let data = bytes.get(pos + 8..pos + 8 + length)?;
+            if &bytes[pos..pos + 4] == b"MTrk" {
+                file.read_track(data, division)?;
+            }
+            pos += 8 + length;
+        }
+        file.notes.sort_by_key(|n| (n.start, n.pitch));
+        file.cursor = file
+            .notes
+            .iter()
+            .map(|n| n.start + n.length)
+            .max()
+            .unwrap_or(0);
+        Some(file)
+    }
+
+    pub fn bar_and_beat(tick: u32) -> (u32, u32) {
+        /* both counted from 1, in 4/4 */
+        let bar = tick / MidiFile::TICKS_PER_BAR + 1;
+        let beat = tick % MidiFile::TICKS_PER_BAR / MidiFile::TICKS_PER_QUARTER + 1;
+        (bar, beat)
+    }
+    fn onsets(&self) -> Vec<Vec<&MidiNote>> {
+        /* the notes grouped by when they start, a strum being one group */
+        let mut groups: Vec<Vec<&MidiNote>> = vec![];
+        for note in &self.notes {
+            match groups.last_mut() {
+                Some(group) if note.start < group[0].start + MidiFile::STRUM_TICKS => {
+                    group.push(note)
+                }
+                _ => groups.push(vec![note]),
+            }
+        }
+        groups
+    }
+    pub fn chord_events(&self) -> Vec<(u32, Vec<Pitch>)> {
+        /* The start and the sounding pitches, lowest first, of every
+         * onset with at least three pitch classes, counting notes held
+         * from before. An onset that sounds like the one before adds
+         * nothing.
+         */
+        let mut events: Vec<(u32, Vec<Pitch>)> = vec![];
+        for group in self.onsets() {
+            let start = group[0].start;
+            let held = self
+                .notes
+                .iter()
+                .filter(|n| n.start < start && n.start + n.length > start);
+            let mut pitches = group
+                .iter()
+                .map(|n| n.pitch)
+                .chain(held.map(|n| n.pitch))
+                .collect::<Vec<_>>();
+            pitches.sort();
+            pitches.dedup();
+            let mut pitch_classes = pitches.iter().map(|p| p.pitch_class()).collect::<Vec<_>>();
+            pitch_classes.sort();
+            pitch_classes.dedup();
+            if pitch_classes.len() < 3 {
+                continue;
+            }
+            if let Some((_, last)) = events.last() {
+                if *last == pitches {
+                    continue;
+                }
+            }
+            events.push((start, pitches));
+        }
+        events
+    }
+    pub fn melody(&self) -> Vec<(u32, Pitch)> {
+        /* the highest note of every onset */
+        self.onsets()
+            .iter()
+            .map(|group| {
+                let top = group.iter().max_by_key(|n| n.pitch).unwrap();
+                (group[0].start, top.pitch)
+            })
+            .collect()
+    }
+}
+#[test]
+fn test_to_bytes() {
+    assert_eq!(MidiFile::variable_length(0), vec![0x00]);
+    assert_eq!(MidiFile::variable_length(480), vec![0x83, 0x60]);
+    assert_eq!(
+        MidiFile::variable_length(0x200000),
+        vec![0x81, 0x80, 0x80, 0x00]
+    );
+
+    let options = MidiOptions {
+        format: 0,
+        tempo: 120,
+        strum_ms: 250,
+        ..Default::default()
+    };
+    let mut midi = MidiFile::new(&options);
+    let e_minor = [40, 47, 52]
+        .iter()
+        .map(|m| Pitch { midi: *m })
+        .collect::<Vec<_>>();
+    midi.add_strum(&e_minor, 4.0);
+    /* half a beat between the strings at 120 bpm */
+    let starts = midi.notes.iter().map(|n| n.start).collect::<Vec<_>>();
+    assert_eq!(starts, vec![0, 240, 480]);
+    midi.add_run(&vec![Pitch { midi: 64 }], 1.0);
+    assert_eq!(midi.notes[3].start, 1920);
+
+    let bytes = midi.to_bytes();
+    assert_eq!(
+        &bytes[..14],
+        b"MThd\x00\x00\x00\x06\x00\x00\x00\x01\x01\xe0"
+    );
+    assert_eq!(&bytes[14..18], b"MTrk");
+    assert_eq!(&bytes[22..29], &[0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20]);
+    assert!(bytes.ends_with(&[0x00, 0xff, 0x2f, 0x00]));
+    let format_1 = MidiFile {
+        options: MidiOptions::default(),
+        ..midi
+    };
+    assert_eq!(&format_1.to_bytes()[8..12], &[0x00, 0x01, 0x00, 0x02]);
+}
+#[test]
+fn test_parse() {
+    let mut midi = MidiFile::new(&MidiOptions {
+        tempo: 90,
+        ..Default::default()
+    });
+    let pitches = |keys: &[i32]| keys.iter().map(|m| Pitch { midi: *m }).collect::<Vec<_>>();
+    midi.add_strum(&pitches(&[45, 52, 57, 60, 64]), 4.0);
+    midi.add_run(&pitches(&[69, 67]), 1.0);
+    let read = MidiFile::parse(&midi.to_bytes()).unwrap();
+    assert_eq!(read.options.tempo, 90);
+    assert_eq!(read.options.program, 25);
+    assert_eq!(read.notes.len(), 7);
+    assert_eq!(read.notes[1].start, 11);
+    assert_eq!(read.notes[6].length, 480);
+    let chords = read.chord_events();
+    assert_eq!(chords.len(), 1);
+    assert_eq!(chords[0].1, pitches(&[45, 52, 57, 60, 64]));
+    let melody = read.melody();
+    assert_eq!(
+        melody,
+        vec![
+            (0, Pitch { midi: 64 }),
+            (1920, Pitch { midi: 69 }),
+            (2400, Pitch { midi: 67 })
+        ]
+    );
+    assert_eq!(MidiFile::bar_and_beat(2400), (2, 2));
+    assert!(MidiFile::parse(b"RIFF").is_none());
+
+    /* running status and note on with velocity 0 as note off */
+    let track = [
+        0x00, 0x91, 60, 100, 0x83, 0x60, 60, 0, 0x00, 0xff, 0x2f, 0x00,
+    ];
+    let mut bytes = b"MThd\x00\x00\x00\x06\x00\x00\x00\x01\x00\x60".to_vec();
+    bytes.extend(b"MTrk\x00\x00\x00\x0c");
+    bytes.extend(&track);
+    let read = MidiFile::parse(&bytes).unwrap();
+    assert_eq!(read.notes.len(), 1);
+    assert_eq!(read.notes[0].length, 2400);
+}
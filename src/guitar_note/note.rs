@@ -37,6 +37,13 @@ impl Note {
             semitones: self.semitones % 12,
         };
     }
+    pub fn pitch_class(self) -> Note {
+        /* Like no_octaves, but always in 0..12, also for negative notes.
+         */
+        Note {
+            semitones: self.semitones.rem_euclid(12),
+        }
+    }
     pub fn regauge_if_negative(&self) -> Note {
         if self.semitones < 0 {
             return self.regauge();
@@ -50,7 +57,7 @@ impl Note {
     pub fn to_string(&self) -> &str {
         /* Returns string of raw note.
          */
-        let st = (self.semitones % 12).abs();
+        let st = self.semitones.rem_euclid(12);
         return Note::NAMES[st as usize];
     }
 }
@@ -0,0 +1,129 @@
+use super::note::Note;
+use super::spelling::Spelling;
+use std::fmt;
+
+/* Pitches
+ * A Note is a number of semitones above the C below the low E string
+ * of a guitar (C2). A Pitch is the absolute MIDI key number, which maps
+ * one to one to scientific pitch notation: C-1 is 0, A4 is 69, G9 is 127.
+ */
+#[derive(Eq, PartialEq, PartialOrd, Ord, Copy, Clone, Debug, Hash)]
+pub struct Pitch {
+    pub midi: i32,
+}
+
+impl Pitch {
+    pub const REFERENCE_A4: f64 = 440.0;
+    const A4_MIDI: i32 = 69;
+    const NOTE_ZERO_MIDI: i32 = 36;
+    const MAX_MIDI: i32 = 127;
+
+    pub fn from_midi(midi: i32) -> Option<Pitch> {
+        match (0..=Pitch::MAX_MIDI).contains(&midi) {
+            true => Some(Pitch { midi }),
+            false => None,
+        }
+    }
+    pub fn from_note(note: Note) -> Pitch {
+        Pitch {
+            midi: note.semitones + Pitch::NOTE_ZERO_MIDI,
+        }
+    }
+    pub fn to_note(self) -> Note {
+        Note {
+            semitones: self.midi - Pitch::NOTE_ZERO_MIDI,
+        }
+    }
+    pub fn pitch_class(&self) -> Note {
+        self.to_note().pitch_class()
+    }
+    pub fn octave(&self) -> i32 {
+        self.midi.div_euclid(12) - 1
+    }
+
+    pub fn from_string(name: &str) -> Option<Pitch> {
+        /* Parses scientific pitch notation such as E2, a4, C#-1 or Bb3.
+         */
+        let mut chars = name.chars().peekable();
+        let letter = chars.next()?.to_ascii_lowercase();
+        let natural = Note::from_string(&letter.to_string())?;
+        let mut accidentals = 0;
+        while let Some(c) = chars.peek() {
+            match c {
+                '#' => accidentals += 1,
+                'b' => accidentals -= 1,
+                _ => break,
+            };
+            chars.next();
+        }
+        let octave = chars.collect::<String>().parse::<i32>().ok()?;
+        // the octave number belongs to the letter, so Cb4 is B3
+        Pitch::from_midi((octave + 1) * 12 + natural.semitones + accidentals)
+    }
+    pub fn spelled(&self, spelling: &[Spelling]) -> String {
+        /* The name with the spelling of its pitch class, e.g. Bb3 rather
+         * than A#3, or with the usual name of that key if there is none.
+         * The octave goes with the letter, so that Cb4 stays Cb4.
+         */
+        let name = spelling
+            .iter()
+            .find(|s| s.pitch_class() == self.pitch_class())
+            .copied()
+            .unwrap_or_else(|| Spelling::of_root(self.pitch_class(), false));
+        let octave = (self.midi - name.accidentals()).div_euclid(12) - 1;
+        format!("{}{}", name.capitalized(), octave)
+    }
+
+    pub fn frequency(&self, reference_a4: f64) -> f64 {
+        let semitones_from_a4 = (self.midi - Pitch::A4_MIDI) as f64;
+        reference_a4 * (semitones_from_a4 / 12.0).exp2()
+    }
+    pub fn from_frequency(frequency: f64, reference_a4: f64) -> Option<Pitch> {
+        /* The closest equal tempered pitch.
+         */
+        if frequency <= 0.0 {
+            return None;
+        }
+        let semitones_from_a4 = 12.0 * (frequency / reference_a4).log2();
+        Pitch::from_midi(Pitch::A4_MIDI + semitones_from_a4.round() as i32)
+    }
+}
+impl fmt::Display for Pitch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            self.pitch_class().to_string().to_uppercase(),
+            self.octave()
+        )
+    }
+}
+#[test]
+fn test_pitch() {
+    let low_e = Pitch::from_note(Note { semitones: 4 });
+    assert_eq!(low_e.to_string(), "E2");
+    assert_eq!(low_e.midi, 40);
+    assert_eq!(Pitch::from_string("e2"), Some(low_e));
+    assert_eq!(Pitch::from_string("A4").unwrap().midi, 69);
+    assert_eq!(Pitch::from_string("C-1").unwrap().midi, 0);
+    assert_eq!(Pitch::from_string("G9").unwrap().midi, 127);
+    assert_eq!(Pitch::from_string("G#9"), None);
+    assert_eq!(Pitch::from_string("Cb4").unwrap().to_string(), "B3");
+    assert_eq!(Pitch::from_string("Bb3").unwrap().to_string(), "A#3");
+    assert_eq!(Pitch::from_string("E"), None);
+    let b_flat = Pitch::from_string("Bb3").unwrap();
+    assert_eq!(b_flat.spelled(&[]), "Bb3");
+    let c_flat = Spelling::from_string("cb").unwrap();
+    assert_eq!(Pitch::from_string("B3").unwrap().spelled(&[c_flat]), "Cb4");
+
+    let a4 = Pitch::from_midi(69).unwrap();
+    assert!((a4.frequency(Pitch::REFERENCE_A4) - 440.0).abs() < 1e-9);
+    assert!((a4.frequency(432.0) - 432.0).abs() < 1e-9);
+    assert!((low_e.frequency(Pitch::REFERENCE_A4) - 82.407).abs() < 1e-3);
+    assert_eq!(
+        Pitch::from_frequency(261.0, Pitch::REFERENCE_A4)
+            .unwrap()
+            .to_string(),
+        "C4"
+    );
+}
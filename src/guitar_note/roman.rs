@@ -0,0 +1,433 @@
+use super::chord::Chord;
+use super::note::Note;
+use super::scale::{Scale, ScaleType};
+use super::spelling::Spelling;
+use std::fmt;
+
+/* Roman numerals
+ * A chord named by the scale degree of its root in a key, e.g. ii7,
+ * V6/5 or bVII. Secondary chords name the chord they lead to (V7/V).
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum TriadSymbol {
+    none,
+    diminished,
+    augmented,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RomanNumeral {
+    /* -1 for bVII, 1 for #IV */
+    accidental: i32,
+    /* 0 is the tonic */
+    degree: usize,
+    /* a major or suspended third, e.g. IV rather than iv */
+    upper: bool,
+    triad: TriadSymbol,
+    /* semitones above the root: 9 (diminished), 10 or 11 */
+    seventh: Option<i32>,
+    /* 9, 11 or 13 */
+    extension: Option<i32>,
+    /* 2 or 4 */
+    sus: Option<i32>,
+    /* 0 for root position, 1 for the third in the bass, ... */
+    inversion: usize,
+    secondary: Option<Box<RomanNumeral>>,
+}
+
+impl RomanNumeral {
+    const NUMERALS: [&'static str; 7] = ["I", "II", "III", "IV", "V", "VI", "VII"];
+    /* (figure, inversion, has a seventh) */
+    const FIGURES: [(&'static str, usize, bool); 11] = [
+        ("6/5", 1, true),
+        ("65", 1, true),
+        ("4/3", 2, true),
+        ("43", 2, true),
+        ("4/2", 3, true),
+        ("42", 3, true),
+        ("6/4", 2, false),
+        ("64", 2, false),
+        ("7", 0, true),
+        ("6", 1, false),
+        ("2", 3, true),
+    ];
+
+    fn plain(degree: usize, upper: bool, triad: TriadSymbol) -> RomanNumeral {
+        RomanNumeral {
+            accidental: 0,
+            degree,
+            upper,
+            triad,
+            seventh: None,
+            extension: None,
+            sus: None,
+            inversion: 0,
+            secondary: None,
+        }
+    }
+    fn diatonic_triad(scale: &Scale, degree: usize) -> RomanNumeral {
+        /* The triad the key builds on a degree, e.g. ii in major.
+         */
+        let notes = scale.get_notes();
+        let above = |steps: usize| (notes[(degree + steps) % 7] - notes[degree]).pitch_class();
+        let third = above(2).semitones;
+        let fifth = above(4).semitones;
+        let triad = match fifth {
+            6 => TriadSymbol::diminished,
+            8 => TriadSymbol::augmented,
+            _ => TriadSymbol::none,
+        };
+        RomanNumeral::plain(degree, third == 4, triad)
+    }
+    fn degree_in(scale: &Scale, chord: &Chord) -> (usize, i32) {
+        /* The degree of the chord root and how far it is altered. The
+         * root is spelled with the letter closest to a degree of the
+         * key, e.g. Bb is bVII in C major.
+         */
+        let root = chord.get_notes()[0];
+        let tonic = scale.spelling()[0];
+        let notes = scale.get_notes();
+        Spelling::candidates(root)
+            .iter()
+            .map(|candidate| {
+                let degree = (candidate.letter() - tonic.letter()).rem_euclid(7) as usize;
+                let mut accidental = (root - notes[degree]).pitch_class().semitones;
+                if accidental > 6 {
+                    accidental -= 12;
+                }
+                (degree, accidental, *candidate != chord.root_spelling())
+            })
+            .min_by_key(|(_, accidental, other)| (accidental.abs(), *other))
+            .map(|(degree, accidental, _)| (degree, accidental))
+            .unwrap()
+    }
+    fn of_chord(chord: &Chord, degree: usize, accidental: i32) -> RomanNumeral {
+        let root = chord.get_notes()[0];
+        let semitones = chord
+            .get_notes()
+            .iter()
+            .map(|n| (*n - root).semitones)
+            .collect::<Vec<_>>();
+        let has = |pc: i32| semitones.iter().any(|s| s.rem_euclid(12) == pc);
+        let upper = !has(3);
+        let sus = match (has(3) || has(4), has(5), has(2)) {
+            (true, _, _) => None,
+            (false, true, _) => Some(4),
+            (false, false, true) => Some(2),
+            _ => None,
+        };
+        let triad = match (has(3), has(4), has(6), has(7), has(8)) {
+            (true, false, true, false, _) => TriadSymbol::diminished,
+            (false, true, false, false, true) => TriadSymbol::augmented,
+            _ => TriadSymbol::none,
+        };
+        let seventh = match (has(11), has(10), triad == TriadSymbol::diminished && has(9)) {
+            (true, _, _) => Some(11),
+            (false, true, _) => Some(10),
+            (false, false, true) => Some(9),
+            _ => None,
+        };
+        let extension = match seventh {
+            Some(_) => semitones
+                .iter()
+                .filter_map(|s| match s {
+                    13..=15 => Some(9),
+                    17..=18 => Some(11),
+                    20..=21 => Some(13),
+                    _ => None,
+                })
+                .max(),
+            None => None,
+        };
+        let inversion = match chord.get_bass().map(|b| (b - root).pitch_class().semitones) {
+            Some(3) | Some(4) => 1,
+            Some(2) | Some(5) if sus.is_some() => 1,
+            Some(6) | Some(7) | Some(8) => 2,
+            Some(9) | Some(10) | Some(11) if seventh.is_some() => 3,
+            _ => 0,
+        };
+        RomanNumeral {
+            accidental,
+            degree,
+            upper,
+            triad,
+            seventh,
+            extension,
+            sus,
+            inversion,
+            secondary: None,
+        }
+    }
+    pub fn analyze(chord: &Chord, scale: &Scale) -> RomanNumeral {
+        /* Chords outside the key that lead a fifth down (V7/V) or a
+         * semitone up (vii°7/V) to a major or minor chord of the key
+         * are written as secondary chords.
+         */
+        let (degree, accidental) = RomanNumeral::degree_in(scale, chord);
+        let scale_notes = scale
+            .get_notes()
+            .iter()
+            .map(|n| n.pitch_class())
+            .collect::<Vec<_>>();
+        let diatonic = chord
+            .get_notes()
+            .iter()
+            .all(|n| scale_notes.contains(&n.pitch_class()));
+        let numeral = RomanNumeral::of_chord(chord, degree, accidental);
+        if diatonic {
+            return numeral;
+        }
+        let root = chord.get_notes()[0];
+        let secondary = match (chord.is_dominant(), numeral.triad) {
+            (true, _) => Some((5, 4)),
+            (false, TriadSymbol::diminished) if !numeral.upper => Some((1, 6)),
+            _ => None,
+        };
+        if let Some((semitones, own_degree)) = secondary {
+            let target_root = (root + Note { semitones }).pitch_class();
+            if let Some(target) = scale_notes[..7].iter().position(|n| *n == target_root) {
+                let target = RomanNumeral::diatonic_triad(scale, target);
+                if target.degree != 0 && target.triad == TriadSymbol::none {
+                    return RomanNumeral {
+                        accidental: 0,
+                        degree: own_degree,
+                        secondary: Some(Box::new(target)),
+                        ..numeral
+                    };
+                }
+            }
+        }
+        numeral
+    }
+    pub fn from_string(numeral: &str) -> Option<RomanNumeral> {
+        /* Parses numerals such as ii7, V6/5, bVII, viiø7, IVmaj7 or V7/V.
+         */
+        let (accidental, mut rest) = match numeral.chars().next()? {
+            'b' => (-1, &numeral[1..]),
+            '#' => (1, &numeral[1..]),
+            _ => (0, numeral),
+        };
+        let n_letters = rest.chars().take_while(|c| "IViv".contains(*c)).count();
+        let letters = &rest[..n_letters];
+        let upper = letters.chars().all(|c| c.is_uppercase());
+        if !upper && letters.chars().any(|c| c.is_uppercase()) {
+            return None;
+        }
+        let degree = RomanNumeral::NUMERALS
+            .iter()
+            .position(|n| *n == letters.to_uppercase())?;
+        rest = &rest[n_letters..];
+        let mut result = RomanNumeral::plain(degree, upper, TriadSymbol::none);
+        result.accidental = accidental;
+        let mut half_diminished = false;
+        if let Some(r) = ["°", "o"].iter().find_map(|t| rest.strip_prefix(t)) {
+            result.triad = TriadSymbol::diminished;
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("ø") {
+            result.triad = TriadSymbol::diminished;
+            half_diminished = true;
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("+") {
+            result.triad = TriadSymbol::augmented;
+            rest = r;
+        }
+        let mut major_seventh = false;
+        if let Some(r) = ["maj", "M"].iter().find_map(|t| rest.strip_prefix(t)) {
+            major_seventh = true;
+            rest = r;
+        }
+        let mut has_seventh = half_diminished || major_seventh;
+        if let Some(r) = ["13", "11", "9"].iter().find_map(|t| rest.strip_prefix(t)) {
+            result.extension = rest[..rest.len() - r.len()].parse::<i32>().ok();
+            has_seventh = true;
+            rest = r;
+        } else if let Some((figure, inversion, seventh)) = RomanNumeral::FIGURES
+            .iter()
+            .find(|(figure, _, _)| rest.starts_with(figure))
+        {
+            result.inversion = *inversion;
+            has_seventh = has_seventh || *seventh;
+            rest = &rest[figure.len()..];
+        }
+        if has_seventh {
+            result.seventh = match (major_seventh, half_diminished, result.triad) {
+                (true, _, _) => Some(11),
+                (false, false, TriadSymbol::diminished) => Some(9),
+                _ => Some(10),
+            };
+        }
+        if let Some(r) = rest.strip_prefix("sus") {
+            let (sus, r) = match r.chars().next() {
+                Some('2') => (2, &r[1..]),
+                Some('4') => (4, &r[1..]),
+                _ => (4, r),
+            };
+            result.sus = Some(sus);
+            rest = r;
+        }
+        if let Some(r) = rest.strip_prefix("/") {
+            result.secondary = Some(Box::new(RomanNumeral::from_string(r)?));
+            rest = "";
+        }
+        match rest.is_empty() {
+            true => Some(result),
+            false => None,
+        }
+    }
+    fn root_in(&self, scale: &Scale) -> Option<Spelling> {
+        /* The root spelled in the key, or in the key of the chord a
+         * secondary chord leads to: major for V/V, harmonic minor for
+         * V/ii.
+         */
+        let spelling = match &self.secondary {
+            Some(target) => {
+                let target_root = target.root_in(scale)?;
+                let target_type = match target.upper {
+                    true => ScaleType::major,
+                    false => ScaleType::harmonic_minor,
+                };
+                Scale::from_type_and_root(target_root.pitch_class(), target_type).spelling()
+            }
+            None => scale.spelling(),
+        };
+        Some(spelling.get(self.degree)?.above(self.accidental, 1))
+    }
+    pub fn to_chord(&self, scale: &Scale) -> Option<(Spelling, Chord)> {
+        /* The chord and its root spelled in the key, e.g. ii in Bb
+         * major is Cm.
+         */
+        let root = self.root_in(scale)?;
+        let extension = self.extension.unwrap_or(7);
+        let quality = match (self.triad, self.seventh) {
+            (TriadSymbol::diminished, Some(10)) => String::from("m7b5"),
+            (TriadSymbol::diminished, Some(9)) => String::from("dim7"),
+            (TriadSymbol::diminished, _) => String::from("dim"),
+            (triad, seventh) => {
+                let third = match (triad, self.upper) {
+                    (TriadSymbol::augmented, _) => "aug",
+                    (_, true) => "",
+                    (_, false) => "m",
+                };
+                let seventh = match seventh {
+                    Some(11) => format!("maj{}", extension),
+                    Some(_) => extension.to_string(),
+                    None => String::from(""),
+                };
+                format!("{}{}", third, seventh)
+            }
+        };
+        let sus = match self.sus {
+            Some(sus) => format!("sus{}", sus),
+            None => String::from(""),
+        };
+        let symbol = format!("{}{}{}", root.capitalized(), quality, sus);
+        let chord = Chord::from_symbol(&symbol)?;
+        if self.inversion == 0 {
+            return Some((root, chord));
+        }
+        let bass = chord.spelling(&root).get(self.inversion)?.pitch_class();
+        Some((root, chord.with_bass(bass)))
+    }
+}
+impl fmt::Display for RomanNumeral {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let accidental = match self.accidental {
+            -1 => "b",
+            1 => "#",
+            _ => "",
+        };
+        let numeral = match self.upper {
+            true => RomanNumeral::NUMERALS[self.degree].to_owned(),
+            false => RomanNumeral::NUMERALS[self.degree].to_lowercase(),
+        };
+        let symbol = match (self.triad, self.seventh) {
+            (TriadSymbol::diminished, Some(10)) => "ø",
+            (TriadSymbol::diminished, _) => "°",
+            (TriadSymbol::augmented, _) => "+",
+            (TriadSymbol::none, _) => "",
+        };
+        let major_seventh = match self.seventh {
+            Some(11) => "maj",
+            _ => "",
+        };
+        let figure = match (self.seventh, self.inversion) {
+            (None, 0) => String::from(""),
+            (None, 1) => String::from("6"),
+            (None, _) => String::from("6/4"),
+            (Some(_), 0) => self.extension.unwrap_or(7).to_string(),
+            (Some(_), 1) => String::from("6/5"),
+            (Some(_), 2) => String::from("4/3"),
+            (Some(_), _) => String::from("4/2"),
+        };
+        let sus = match self.sus {
+            Some(sus) => format!("sus{}", sus),
+            None => String::from(""),
+        };
+        let secondary = match &self.secondary {
+            Some(target) => format!("/{}", target),
+            None => String::from(""),
+        };
+        write!(
+            f,
+            "{}{}{}{}{}{}{}",
+            accidental, numeral, symbol, major_seventh, figure, sus, secondary
+        )
+    }
+}
+#[test]
+fn test_analyze() {
+    let c_major = Scale::from_type_and_root(Note { semitones: 0 }, ScaleType::major);
+    let analyze = |symbols: &[&str]| {
+        symbols
+            .iter()
+            .map(|s| RomanNumeral::analyze(&Chord::from_symbol(s).unwrap(), &c_major).to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    assert_eq!(analyze(&["C", "Am", "F", "G"]), "I vi IV V");
+    assert_eq!(analyze(&["Dm7", "G7", "Cmaj7"]), "ii7 V7 Imaj7");
+    assert_eq!(analyze(&["D7", "G", "E7", "Am"]), "V7/V V V7/vi vi");
+    assert_eq!(
+        analyze(&["Bb", "Fm", "Eb", "F#dim7"]),
+        "bVII iv bIII vii°7/V"
+    );
+    assert_eq!(analyze(&["C/E", "G7/D", "Bm7b5", "G9"]), "I6 V4/3 viiø7 V9");
+    let a_minor = Scale::from_type_and_root(Note { semitones: 9 }, ScaleType::minor);
+    let e7 = Chord::from_symbol("E7").unwrap();
+    assert_eq!(RomanNumeral::analyze(&e7, &a_minor).to_string(), "V7");
+}
+#[test]
+fn test_to_chord() {
+    let bb_major = Scale::from_type_and_root(Note { semitones: 10 }, ScaleType::major);
+    let chords = [
+        "ii",
+        "V",
+        "I",
+        "ii7",
+        "V7",
+        "Imaj7",
+        "V6/5",
+        "V7/V",
+        "vii°7/ii",
+        "bVII",
+    ]
+    .iter()
+    .map(|n| {
+        let (root, chord) = RomanNumeral::from_string(n)
+            .unwrap()
+            .to_chord(&bb_major)
+            .unwrap();
+        chord.to_string_with_root(&root)
+    })
+    .collect::<Vec<_>>();
+    assert_eq!(
+        chords,
+        vec!["Cm", "F", "Bb", "Cm7", "F7", "Bbmaj7", "F7/A", "C7", "Bdim7", "Ab"]
+    );
+    for numeral in ["I6", "V4/3", "viiø7", "IV", "V7/V", "bVII", "V9sus4"].iter() {
+        let parsed = RomanNumeral::from_string(numeral).unwrap();
+        assert_eq!(parsed.to_string(), *numeral);
+    }
+    assert_eq!(RomanNumeral::from_string("VIII"), None);
+    assert_eq!(RomanNumeral::from_string("Vx"), None);
+}
@@ -2,11 +2,15 @@ extern crate enum_utils;
 extern crate itertools;
 
 use super::note::Note;
+use super::spelling::Spelling;
+use itertools::Itertools;
 /* Scales
  */
-#[derive(Clone, enum_utils::FromStr, enum_utils::IterVariants)]
+#[derive(Clone, Debug, enum_utils::FromStr, enum_utils::IterVariants)]
 pub enum ScaleType {
+    #[enumeration(alias = "aeolian", alias = "natural_minor")]
     minor,
+    #[enumeration(alias = "ionian")]
     major,
     minor_blues,
     major_blues,
@@ -16,6 +20,63 @@ pub enum ScaleType {
     phrygian,
     lydian,
     mixolydian,
+    locrian,
+    /* harmonic minor and its modes */
+    harmonic_minor,
+    locrian_natural6,
+    ionian_augmented,
+    #[enumeration(alias = "ukrainian_dorian")]
+    dorian_sharp4,
+    #[enumeration(alias = "spanish")]
+    phrygian_dominant,
+    lydian_sharp2,
+    altered_diminished,
+    /* melodic minor and its modes */
+    #[enumeration(alias = "jazz_minor")]
+    melodic_minor,
+    dorian_flat2,
+    lydian_augmented,
+    #[enumeration(alias = "overtone")]
+    lydian_dominant,
+    mixolydian_flat6,
+    locrian_sharp2,
+    #[enumeration(alias = "super_locrian")]
+    altered,
+    /* symmetric scales */
+    whole_tone,
+    #[enumeration(alias = "dominant_diminished")]
+    half_whole_diminished,
+    whole_half_diminished,
+    augmented,
+    /* bebop scales */
+    bebop_dominant,
+    bebop_major,
+    bebop_dorian,
+    /* further heptatonic scales */
+    hungarian_minor,
+    #[enumeration(alias = "byzantine")]
+    double_harmonic,
+    neapolitan_minor,
+    neapolitan_major,
+    /* further pentatonic and hexatonic scales */
+    hirajoshi,
+    in_sen,
+    iwato,
+    kumoi,
+    egyptian,
+    prometheus,
+    /* built from a formula, see Scale::from_formula */
+    #[enumeration(skip)]
+    custom,
+}
+
+impl ScaleType {
+    pub fn names() -> Vec<String> {
+        ScaleType::iter()
+            .filter(|t| !matches!(t, ScaleType::custom))
+            .map(|t| format!("{:?}", t))
+            .collect()
+    }
 }
 
 pub struct Scale {
@@ -23,6 +84,58 @@ pub struct Scale {
     pub scale_type: ScaleType,
 }
 
+/* How the chords on each degree of a scale are stacked */
+#[derive(Clone, Copy, Debug, PartialEq, enum_utils::FromStr)]
+pub enum Harmony {
+    triads,
+    #[enumeration(alias = "7")]
+    sevenths,
+    #[enumeration(alias = "9")]
+    ninths,
+    sus2,
+    sus4,
+    quartal,
+}
+
+impl Harmony {
+    fn scale_steps(&self) -> Vec<usize> {
+        /* steps of a seven note scale above the root of each chord */
+        match self {
+            Harmony::triads => vec![0, 2, 4],
+            Harmony::sevenths => vec![0, 2, 4, 6],
+            Harmony::ninths => vec![0, 2, 4, 6, 8],
+            Harmony::sus2 => vec![0, 1, 4],
+            Harmony::sus4 => vec![0, 3, 4],
+            Harmony::quartal => vec![0, 3, 6],
+        }
+    }
+    fn semitones(&self) -> Vec<Vec<i32>> {
+        /* For scales without seven notes: the semitones above the root
+         * each chord tone may have, the first one found is taken, e.g.
+         * a third or else a fourth or second.
+         */
+        let third = vec![4, 3, 5, 2];
+        let fifth = vec![7, 6, 8];
+        let seventh = vec![10, 11, 9];
+        let ninth = vec![14, 13, 15];
+        match self {
+            Harmony::triads => vec![third, fifth],
+            Harmony::sevenths => vec![third, fifth, seventh],
+            Harmony::ninths => vec![third, fifth, seventh, ninth],
+            Harmony::sus2 => vec![vec![2], fifth],
+            Harmony::sus4 => vec![vec![5], fifth],
+            Harmony::quartal => vec![vec![5, 6, 4], vec![10, 11, 9]],
+        }
+    }
+}
+
+/* A scale that contains some of the notes looked for */
+pub struct ScaleMatch {
+    pub scale: Scale,
+    pub matched: usize,
+    pub unused: Vec<Spelling>,
+}
+
 impl Scale {
     const MAJOR_INTERVALS: [i32; 8] = [0, 2, 2, 1, 2, 2, 2, 1];
     const MINOR_INTERVALS: [i32; 8] = [0, 2, 1, 2, 2, 1, 2, 2];
@@ -34,6 +147,26 @@ impl Scale {
     const PHRYGIAN_INTERVALS: [i32; 8] = [0, 1, 2, 2, 2, 1, 2, 2];
     const LYDIAN_INTERVALS: [i32; 8] = [0, 2, 2, 2, 1, 2, 2, 1];
     const MIXOLYDIAN_INTERVALS: [i32; 8] = [0, 2, 2, 1, 2, 2, 1, 2];
+    const HARMONIC_MINOR_INTERVALS: [i32; 8] = [0, 2, 1, 2, 2, 1, 3, 1];
+    const MELODIC_MINOR_INTERVALS: [i32; 8] = [0, 2, 1, 2, 2, 2, 2, 1];
+    const WHOLE_TONE_INTERVALS: [i32; 7] = [0, 2, 2, 2, 2, 2, 2];
+    const HALF_WHOLE_INTERVALS: [i32; 9] = [0, 1, 2, 1, 2, 1, 2, 1, 2];
+    const AUGMENTED_INTERVALS: [i32; 7] = [0, 3, 1, 3, 1, 3, 1];
+    const BEBOP_DOMINANT_INTERVALS: [i32; 9] = [0, 2, 2, 1, 2, 2, 1, 1, 1];
+    const BEBOP_MAJOR_INTERVALS: [i32; 9] = [0, 2, 2, 1, 2, 1, 1, 2, 1];
+    const BEBOP_DORIAN_INTERVALS: [i32; 9] = [0, 2, 1, 1, 1, 2, 2, 1, 2];
+    const HUNGARIAN_MINOR_INTERVALS: [i32; 8] = [0, 2, 1, 3, 1, 1, 3, 1];
+    const DOUBLE_HARMONIC_INTERVALS: [i32; 8] = [0, 1, 3, 1, 2, 1, 3, 1];
+    const NEAPOLITAN_MINOR_INTERVALS: [i32; 8] = [0, 1, 2, 2, 2, 1, 3, 1];
+    const NEAPOLITAN_MAJOR_INTERVALS: [i32; 8] = [0, 1, 2, 2, 2, 2, 2, 1];
+    const HIRAJOSHI_INTERVALS: [i32; 6] = [0, 2, 1, 4, 1, 4];
+    const IN_SEN_INTERVALS: [i32; 6] = [0, 1, 4, 2, 3, 2];
+    const IWATO_INTERVALS: [i32; 6] = [0, 1, 4, 1, 4, 2];
+    const KUMOI_INTERVALS: [i32; 6] = [0, 2, 1, 4, 2, 3];
+    const EGYPTIAN_INTERVALS: [i32; 6] = [0, 2, 3, 2, 3, 2];
+    const PROMETHEUS_INTERVALS: [i32; 7] = [0, 2, 2, 2, 3, 1, 2];
+    /* semitones of the degrees of the major scale */
+    const MAJOR_DEGREES: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
     const DEGREE: [&'static str; 13] = [
         "1", "2b", "2", "3b", "3", "4", "5b", "5", "6b", "6", "7b", "7", "8",
     ];
@@ -58,94 +191,526 @@ impl Scale {
         };
     }
 
+    fn mode(parent: &[i32], degree: usize) -> Vec<i32> {
+        /* The steps of the mode of a parent scale that starts on the
+         * given degree, 1 being the parent scale itself.
+         */
+        let steps = &parent[1..];
+        let mut result = vec![0];
+        result.extend(steps.iter().cycle().skip(degree - 1).take(steps.len()));
+        result
+    }
+
     pub fn from_type_and_root(root: Note, scale_type: ScaleType) -> Scale {
-        return match scale_type {
-            ScaleType::minor => {
-                Scale::from_intervals(root.semitones, &Scale::MINOR_INTERVALS, scale_type)
-            }
-            ScaleType::major => {
-                Scale::from_intervals(root.semitones, &Scale::MAJOR_INTERVALS, scale_type)
-            }
-            ScaleType::minor_pentatonic => Scale::from_intervals(
-                root.semitones,
-                &Scale::MINOR_PENTATONIC_INTERVALS,
-                scale_type,
-            ),
-            ScaleType::major_pentatonic => Scale::from_intervals(
-                root.semitones,
-                &Scale::MAJOR_PENTATONIC_INTERVALS,
-                scale_type,
-            ),
-            ScaleType::minor_blues => {
-                Scale::from_intervals(root.semitones, &Scale::BLUES_MINOR_INTERVALS, scale_type)
-            }
-            ScaleType::major_blues => {
-                Scale::from_intervals(root.semitones, &Scale::BLUES_MAJOR_INTERVALS, scale_type)
-            }
-            ScaleType::dorian => {
-                Scale::from_intervals(root.semitones, &Scale::DORIAN_INTERVALS, scale_type)
-            }
-            ScaleType::phrygian => {
-                Scale::from_intervals(root.semitones, &Scale::PHRYGIAN_INTERVALS, scale_type)
-            }
-            ScaleType::lydian => {
-                Scale::from_intervals(root.semitones, &Scale::LYDIAN_INTERVALS, scale_type)
+        let intervals = match scale_type {
+            ScaleType::minor => Scale::MINOR_INTERVALS.to_vec(),
+            ScaleType::major => Scale::MAJOR_INTERVALS.to_vec(),
+            ScaleType::minor_pentatonic => Scale::MINOR_PENTATONIC_INTERVALS.to_vec(),
+            ScaleType::major_pentatonic => Scale::MAJOR_PENTATONIC_INTERVALS.to_vec(),
+            ScaleType::minor_blues => Scale::BLUES_MINOR_INTERVALS.to_vec(),
+            ScaleType::major_blues => Scale::BLUES_MAJOR_INTERVALS.to_vec(),
+            ScaleType::dorian => Scale::DORIAN_INTERVALS.to_vec(),
+            ScaleType::phrygian => Scale::PHRYGIAN_INTERVALS.to_vec(),
+            ScaleType::lydian => Scale::LYDIAN_INTERVALS.to_vec(),
+            ScaleType::mixolydian => Scale::MIXOLYDIAN_INTERVALS.to_vec(),
+            ScaleType::locrian => Scale::mode(&Scale::MAJOR_INTERVALS, 7),
+            ScaleType::harmonic_minor => Scale::HARMONIC_MINOR_INTERVALS.to_vec(),
+            ScaleType::locrian_natural6 => Scale::mode(&Scale::HARMONIC_MINOR_INTERVALS, 2),
+            ScaleType::ionian_augmented => Scale::mode(&Scale::HARMONIC_MINOR_INTERVALS, 3),
+            ScaleType::dorian_sharp4 => Scale::mode(&Scale::HARMONIC_MINOR_INTERVALS, 4),
+            ScaleType::phrygian_dominant => Scale::mode(&Scale::HARMONIC_MINOR_INTERVALS, 5),
+            ScaleType::lydian_sharp2 => Scale::mode(&Scale::HARMONIC_MINOR_INTERVALS, 6),
+            ScaleType::altered_diminished => Scale::mode(&Scale::HARMONIC_MINOR_INTERVALS, 7),
+            ScaleType::melodic_minor => Scale::MELODIC_MINOR_INTERVALS.to_vec(),
+            ScaleType::dorian_flat2 => Scale::mode(&Scale::MELODIC_MINOR_INTERVALS, 2),
+            ScaleType::lydian_augmented => Scale::mode(&Scale::MELODIC_MINOR_INTERVALS, 3),
+            ScaleType::lydian_dominant => Scale::mode(&Scale::MELODIC_MINOR_INTERVALS, 4),
+            ScaleType::mixolydian_flat6 => Scale::mode(&Scale::MELODIC_MINOR_INTERVALS, 5),
+            ScaleType::locrian_sharp2 => Scale::mode(&Scale::MELODIC_MINOR_INTERVALS, 6),
+            ScaleType::altered => Scale::mode(&Scale::MELODIC_MINOR_INTERVALS, 7),
+            ScaleType::whole_tone => Scale::WHOLE_TONE_INTERVALS.to_vec(),
+            ScaleType::half_whole_diminished => Scale::HALF_WHOLE_INTERVALS.to_vec(),
+            ScaleType::whole_half_diminished => Scale::mode(&Scale::HALF_WHOLE_INTERVALS, 2),
+            ScaleType::augmented => Scale::AUGMENTED_INTERVALS.to_vec(),
+            ScaleType::bebop_dominant => Scale::BEBOP_DOMINANT_INTERVALS.to_vec(),
+            ScaleType::bebop_major => Scale::BEBOP_MAJOR_INTERVALS.to_vec(),
+            ScaleType::bebop_dorian => Scale::BEBOP_DORIAN_INTERVALS.to_vec(),
+            ScaleType::hungarian_minor => Scale::HUNGARIAN_MINOR_INTERVALS.to_vec(),
+            ScaleType::double_harmonic => Scale::DOUBLE_HARMONIC_INTERVALS.to_vec(),
+            ScaleType::neapolitan_minor => Scale::NEAPOLITAN_MINOR_INTERVALS.to_vec(),
+            ScaleType::neapolitan_major => Scale::NEAPOLITAN_MAJOR_INTERVALS.to_vec(),
+            ScaleType::hirajoshi => Scale::HIRAJOSHI_INTERVALS.to_vec(),
+            ScaleType::in_sen => Scale::IN_SEN_INTERVALS.to_vec(),
+            ScaleType::iwato => Scale::IWATO_INTERVALS.to_vec(),
+            ScaleType::kumoi => Scale::KUMOI_INTERVALS.to_vec(),
+            ScaleType::egyptian => Scale::EGYPTIAN_INTERVALS.to_vec(),
+            ScaleType::prometheus => Scale::PROMETHEUS_INTERVALS.to_vec(),
+            ScaleType::custom => vec![0, 12],
+        };
+        Scale::from_intervals(root.semitones, &intervals, scale_type)
+    }
+    pub fn from_formula(root: Note, formula: &str) -> Option<Scale> {
+        /* Builds a scale from a list of degrees ("1 2 b3 4 5 b6 7", 3b
+         * works as well) or of steps ("W H W W H A H", also T and S).
+         */
+        let tokens = formula
+            .split(|c: char| c.is_whitespace() || c == ',' || c == '-')
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>();
+        if tokens.is_empty() {
+            return None;
+        }
+        let semitones = match tokens[0].chars().any(|c| c.is_ascii_digit()) {
+            true => Scale::parse_degrees(&tokens)?,
+            false => Scale::parse_steps(&tokens)?,
+        };
+        if semitones[0] != 0
+            || semitones.windows(2).any(|pair| pair[0] >= pair[1])
+            || *semitones.last().unwrap() >= 12
+        {
+            return None;
+        }
+        let mut intervals = vec![0];
+        intervals.extend(semitones.windows(2).map(|pair| pair[1] - pair[0]));
+        intervals.push(12 - semitones.last().unwrap());
+        Some(Scale::from_intervals(
+            root.semitones,
+            &intervals,
+            ScaleType::custom,
+        ))
+    }
+    fn parse_degrees(tokens: &Vec<&str>) -> Option<Vec<i32>> {
+        /* Semitones above the root of degrees such as b3, #4 or 7. */
+        tokens
+            .iter()
+            .map(|token| {
+                let digits = token
+                    .chars()
+                    .filter(|c| c.is_ascii_digit())
+                    .collect::<String>();
+                let degree = digits.parse::<usize>().ok().filter(|d| *d >= 1)?;
+                let sharps = token.matches('#').count() as i32;
+                let flats = token.matches('b').count() as i32;
+                if digits.len() + (sharps + flats) as usize != token.len() {
+                    return None;
+                }
+                let major = Scale::MAJOR_DEGREES[(degree - 1) % 7];
+                Some((major + sharps - flats).rem_euclid(12))
+            })
+            .collect()
+    }
+    fn parse_steps(tokens: &Vec<&str>) -> Option<Vec<i32>> {
+        /* Semitones above the root of each step, the last step back
+         * to the octave may be left out.
+         */
+        let steps = tokens
+            .iter()
+            .map(|token| match &token.to_uppercase()[..] {
+                "H" | "S" => Some(1),
+                "W" | "T" => Some(2),
+                "A" => Some(3),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let mut semitones = vec![0];
+        semitones.extend(steps.iter().scan(0, |state, step| {
+            *state += step;
+            Some(*state)
+        }));
+        if *semitones.last().unwrap() == 12 {
+            semitones.pop();
+        }
+        Some(semitones)
+    }
+    pub fn identify(notes: &[Note]) -> Vec<ScaleMatch> {
+        /* Every scale type on every root that shares notes with the
+         * given ones. Scales matching most notes come first, preferably
+         * on the first given note, then those with fewest notes left
+         * over and the more common scale types.
+         */
+        let pitch_classes = notes
+            .iter()
+            .map(|n| n.pitch_class())
+            .unique()
+            .collect::<Vec<_>>();
+        let first = match pitch_classes.first() {
+            Some(first) => *first,
+            None => return vec![],
+        };
+        let mut matches = vec![];
+        for (type_idx, scale_type) in ScaleType::iter().enumerate() {
+            if let ScaleType::custom = scale_type {
+                continue;
             }
-            ScaleType::mixolydian => {
-                Scale::from_intervals(root.semitones, &Scale::MIXOLYDIAN_INTERVALS, scale_type)
+            for root in 0..12 {
+                let scale = Scale::from_type_and_root(Note { semitones: root }, scale_type.clone());
+                let steps = scale.notes.len() - 1;
+                let spelling = scale.spelling();
+                let matched = pitch_classes
+                    .iter()
+                    .filter(|n| scale.notes.iter().any(|s| s.pitch_class() == **n))
+                    .count();
+                if matched == 0 {
+                    continue;
+                }
+                let unused = spelling
+                    .into_iter()
+                    .take(steps)
+                    .filter(|s| !pitch_classes.contains(&s.pitch_class()))
+                    .collect::<Vec<_>>();
+                let rank = (
+                    -(matched as i32),
+                    root != first.semitones,
+                    unused.len(),
+                    type_idx,
+                    root,
+                );
+                matches.push((
+                    rank,
+                    ScaleMatch {
+                        scale,
+                        matched,
+                        unused,
+                    },
+                ));
             }
-        };
+        }
+        matches.sort_by_key(|(rank, _)| *rank);
+        matches.into_iter().map(|(_, m)| m).collect()
+    }
+    pub fn is_heptatonic(&self) -> bool {
+        /* seven notes and the octave */
+        self.notes.len() == 8
     }
 
     fn root(&self) -> Note {
         return self.notes[0].clone();
     }
     pub fn chords_in_scale(&self) -> Vec<String> {
+        self.harmonize(&Harmony::triads)
+    }
+    pub fn harmonize(&self, harmony: &Harmony) -> Vec<String> {
+        /* The chord on every degree. Quartal stacks are written as their
+         * notes, e.g. C-F-B, other stacks that are no known chord as
+         * their notes in parentheses, e.g. (D-G-Ab).
+         */
         use super::chord::Chord;
-        let mut chord_names = vec![];
-        let n_notes = self.notes.len();
-
-        let two_octaves = self
-            .notes
-            .iter()
-            .take(self.notes.len() - 1)
-            .map(|n| *n)
-            .chain(self.notes.iter().map(|n| (*n + Note::octave())))
+        let spelling = self.spelling();
+        let n_steps = self.notes.len() - 1;
+        let three_octaves = (0..3)
+            .flat_map(|octave| {
+                self.notes.iter().take(n_steps).map(move |n| {
+                    *n + Note {
+                        semitones: 12 * octave,
+                    }
+                })
+            })
             .collect::<Vec<Note>>();
-        for i in 0..n_notes {
-            let chords = Chord::find_chord(&vec![
-                two_octaves[i],
-                two_octaves[i + 2],
-                two_octaves[i + 4],
-            ]);
-            let first_chord = chords[0].clone();
-            match first_chord {
-                Some(chord) => chord_names.push(chord.to_string()),
-                None => chord_names.push(String::from("x ")),
+        let spell = |note: &Note| {
+            spelling
+                .iter()
+                .find(|s| s.pitch_class() == note.pitch_class())
+                .copied()
+                .unwrap_or(Spelling::of_root(*note, false))
+        };
+        (0..self.notes.len())
+            .map(|i| {
+                let stack = match self.is_heptatonic() {
+                    true => harmony
+                        .scale_steps()
+                        .iter()
+                        .map(|step| three_octaves[i + step])
+                        .collect::<Vec<_>>(),
+                    false => Scale::stack(&three_octaves[i..], &harmony.semitones()),
+                };
+                let chord = match harmony {
+                    Harmony::quartal => None,
+                    _ => Chord::find_chord(&stack, false)
+                        .into_iter()
+                        .enumerate()
+                        .find_map(|(idx, c)| c.map(|c| (idx, c))),
+                };
+                match chord {
+                    Some((0, chord)) => chord.to_string_with_root(&spelling[i]),
+                    Some((_, chord)) => chord
+                        .with_bass(stack[0])
+                        .to_string_with_root(&spell(&chord.get_notes()[0])),
+                    None => {
+                        let notes = stack
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, n)| match idx {
+                                0 => spelling[i].capitalized(),
+                                _ => spell(n).capitalized(),
+                            })
+                            .join("-");
+                        match harmony {
+                            Harmony::quartal => notes,
+                            _ => format!("({})", notes),
+                        }
+                    }
+                }
+            })
+            .collect()
+    }
+    fn stack(notes: &[Note], semitones: &Vec<Vec<i32>>) -> Vec<Note> {
+        /* Scales without seven notes don't have a third on every other
+         * step, so each chord tone is taken at one of the wanted
+         * distances from the root. The first distance is preferred, then
+         * the ones a third above the chord tone before, so that a major
+         * third gets a #5 and a minor third a b5 where the scale has no
+         * perfect fifth, e.g. C+ in the whole tone scale. Chord tones
+         * the scale lacks are left out.
+         */
+        let root = notes[0];
+        let mut chord = vec![root];
+        for candidates in semitones {
+            let below = *chord.last().unwrap();
+            let found = candidates
+                .iter()
+                .filter_map(|s| notes.iter().find(|n| (**n - root).semitones == *s))
+                .copied()
+                .collect::<Vec<_>>();
+            let is_third = |n: &&Note| matches!((**n - below).semitones, 3 | 4);
+            let tone = match found.first() {
+                Some(first) if (*first - root).semitones == candidates[0] => Some(*first),
+                _ => found
+                    .iter()
+                    .find(is_third)
+                    .or_else(|| found.first())
+                    .copied(),
             };
+            chord.extend(tone);
         }
-        return chord_names;
+        chord
     }
     pub fn note_to_degree(root: &Note, diff: &Note) -> &'static str {
-        return Scale::DEGREE[((*diff - *root).regauge_if_negative().semitones % 12) as usize];
+        Scale::DEGREE[(*diff - *root).pitch_class().semitones as usize]
     }
-    pub fn degrees_in_scale<'a>(&'a self) -> impl Iterator<Item = &'a str> {
+    pub fn degrees_in_scale(&self) -> impl Iterator<Item = String> {
+        /* Seven note scales name every step by its letter, e.g. the
+         * sharpened fourth of lydian is 4# rather than 5b. Other scales
+         * fall back to the flat names.
+         */
         let root_semitone = self.root().clone().semitones;
-
-        return self
+        let semitones = self
             .notes
             .iter()
-            .map(move |i| Scale::DEGREE[((i.semitones - root_semitone) % 13) as usize]);
+            .map(|n| n.semitones - root_semitone)
+            .collect::<Vec<_>>();
+        let degrees = match self.is_heptatonic() {
+            true => semitones
+                .iter()
+                .enumerate()
+                .map(|(step, s)| match step {
+                    7 => String::from("8"),
+                    _ => {
+                        let alteration = s - Scale::MAJOR_DEGREES[step];
+                        let accidental = if alteration > 0 { "#" } else { "b" };
+                        format!(
+                            "{}{}",
+                            step + 1,
+                            accidental.repeat(alteration.unsigned_abs() as usize)
+                        )
+                    }
+                })
+                .collect::<Vec<_>>(),
+            false => semitones
+                .iter()
+                .map(|s| Scale::DEGREE[(s % 13) as usize].to_owned())
+                .collect::<Vec<_>>(),
+        };
+        degrees.into_iter()
     }
 
-    pub fn notes_in_scale<'a>(&'a self) -> impl Iterator<Item = &'a str> {
-        return self.notes.iter().map(|n| n.to_string());
+    fn letter_steps(&self) -> Vec<i32> {
+        /* Seven note scales use every letter once, other scales take
+         * the letter of their degree, e.g. the blue note is a flat five.
+         */
+        if self.is_heptatonic() {
+            return (0..8).collect();
+        }
+        self.degrees_in_scale()
+            .map(|d| d[..1].parse::<i32>().unwrap() - 1)
+            .collect()
+    }
+    pub fn spelling(&self) -> Vec<Spelling> {
+        /* Chooses the spelling of the root that needs the fewest
+         * accidentals in the whole scale, e.g. F major gets a Bb.
+         */
+        let root = self.root();
+        let steps = self.letter_steps();
+        let spell = |root_spelling: &Spelling| {
+            self.notes
+                .iter()
+                .zip(steps.iter())
+                .map(|(n, step)| root_spelling.above((*n - root).semitones, *step + 1))
+                .collect::<Vec<_>>()
+        };
+        let count_accidentals =
+            |spelling: &Vec<Spelling>| spelling.iter().map(|s| s.accidentals().abs()).sum::<i32>();
+        let minor = self.notes.iter().any(|n| (*n - root).semitones == 3);
+        let preferred = Spelling::of_root(root, minor);
+        Spelling::candidates(root)
+            .iter()
+            .map(spell)
+            .min_by_key(|spelling| (count_accidentals(spelling), spelling[0] != preferred))
+            .unwrap()
     }
 }
 #[test]
 fn test_notes_in_scale() {
+    let names = |scale: &Scale| {
+        scale
+            .spelling()
+            .iter()
+            .map(|s| s.capitalized())
+            .collect::<Vec<_>>()
+    };
     let root = Note { semitones: 4 };
     let scale = Scale::from_type_and_root(root, ScaleType::minor);
-    let notes = scale.notes_in_scale().collect::<Vec<_>>();
+    let notes = names(&scale);
     println!("{:?}", notes);
+    assert_eq!(notes, vec!["E", "F#", "G", "A", "B", "C", "D", "E"]);
+
+    let f_major = Scale::from_type_and_root(Note { semitones: 5 }, ScaleType::major);
+    let notes = names(&f_major);
+    assert_eq!(notes, vec!["F", "G", "A", "Bb", "C", "D", "E", "F"]);
+    let chords = f_major.chords_in_scale();
+    assert_eq!(chords[3], "Bb");
+
+    let eb_minor = Scale::from_type_and_root(Note { semitones: 3 }, ScaleType::minor);
+    let notes = names(&eb_minor);
+    assert_eq!(notes, vec!["Eb", "F", "Gb", "Ab", "Bb", "Cb", "Db", "Eb"]);
+
+    let a_blues = Scale::from_type_and_root(Note { semitones: 9 }, ScaleType::minor_blues);
+    let notes = names(&a_blues);
+    assert_eq!(notes, vec!["A", "C", "D", "Eb", "E", "G", "A"]);
+}
+#[test]
+fn test_scale_library() {
+    let names = |scale: &Scale| {
+        scale
+            .spelling()
+            .iter()
+            .map(|s| s.capitalized())
+            .collect::<Vec<_>>()
+    };
+    let a = Note { semitones: 9 };
+    let degrees = |scale_type: ScaleType| {
+        Scale::from_type_and_root(a, scale_type)
+            .degrees_in_scale()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    assert_eq!(degrees(ScaleType::lydian), "1 2 3 4# 5 6 7 8");
+    assert_eq!(degrees(ScaleType::altered), "1 2b 3b 4b 5b 6b 7b 8");
+    assert_eq!(
+        degrees(ScaleType::altered_diminished),
+        "1 2b 3b 4b 5b 6b 7bb 8"
+    );
+    assert_eq!(degrees(ScaleType::whole_tone), "1 2 3 5b 6b 7b 8");
+    assert_eq!(degrees(ScaleType::hirajoshi), "1 2 3b 5 6b 8");
+    assert_eq!(
+        "aeolian".parse::<ScaleType>().ok().map(&degrees),
+        Some(degrees(ScaleType::minor))
+    );
+
+    let harmonic_minor = Scale::from_type_and_root(a, ScaleType::harmonic_minor);
+    let chords = harmonic_minor.chords_in_scale();
+    assert_eq!(chords[2], "C+");
+    assert_eq!(chords[4], "E");
+    let notes = names(&harmonic_minor);
+    assert_eq!(notes[6], "G#");
+
+    let bebop = Scale::from_type_and_root(Note { semitones: 7 }, ScaleType::bebop_dominant);
+    let chords = bebop.chords_in_scale();
+    assert_eq!(chords.len(), 9);
+    assert_eq!(chords[0], "G");
+    let hirajoshi = Scale::from_type_and_root(a, ScaleType::hirajoshi);
+    assert_eq!(hirajoshi.chords_in_scale()[0], "Am");
+}
+#[test]
+fn test_from_formula() {
+    let a = Note { semitones: 9 };
+    let harmonic_minor = Scale::from_type_and_root(a, ScaleType::harmonic_minor);
+    for formula in &[
+        "1 2 b3 4 5 b6 7",
+        "1 2 3b 4 5 6b 7",
+        "W H W W H A H",
+        "w h w w h a",
+    ] {
+        let scale = Scale::from_formula(a, formula).unwrap();
+        assert_eq!(scale.get_notes(), harmonic_minor.get_notes());
+        assert_eq!(scale.chords_in_scale(), harmonic_minor.chords_in_scale());
+    }
+    let hirajoshi = Scale::from_formula(a, "1 2 b3 5 b6").unwrap();
+    assert_eq!(
+        hirajoshi.degrees_in_scale().collect::<Vec<_>>(),
+        vec!["1", "2", "3b", "5", "6b", "8"]
+    );
+    assert!(Scale::from_formula(a, "1 3 2").is_none());
+    assert!(Scale::from_formula(a, "W W W W W W W").is_none());
+    assert!(Scale::from_formula(a, "1 x3").is_none());
+}
+#[test]
+fn test_identify() {
+    let notes = ["a", "c", "d", "e", "g"]
+        .iter()
+        .map(|n| Note::from_string(n).unwrap())
+        .collect::<Vec<_>>();
+    let matches = Scale::identify(&notes);
+    let name = |m: &ScaleMatch| {
+        format!(
+            "{} {:?}",
+            m.scale.spelling()[0].capitalized(),
+            m.scale.scale_type
+        )
+    };
+    assert_eq!(name(&matches[0]), "A minor_pentatonic");
+    assert_eq!(matches[0].matched, 5);
+    assert!(matches[0].unused.is_empty());
+    assert_eq!(name(&matches[1]), "A minor_blues");
+    let a_minor = matches.iter().find(|m| name(m) == "A minor").unwrap();
+    let unused = a_minor
+        .unused
+        .iter()
+        .map(|s| s.capitalized())
+        .collect::<Vec<_>>();
+    assert_eq!(unused, vec!["B", "F"]);
+    assert!(matches.iter().all(|m| m.matched > 0));
+}
+#[test]
+fn test_harmonize() {
+    let c_major = Scale::from_type_and_root(Note { semitones: 0 }, ScaleType::major);
+    assert_eq!(
+        c_major.harmonize(&Harmony::sevenths)[..7],
+        ["Cmaj7", "Dm7", "Em7", "Fmaj7", "G7", "Am7", "Bm7b5"]
+    );
+    assert_eq!(
+        c_major.harmonize(&Harmony::ninths)[..7],
+        ["Cmaj9", "Dm9", "Em7b9", "Fmaj9", "G9", "Am9", "Bm7b5b9"]
+    );
+    assert_eq!(c_major.harmonize(&Harmony::sus4)[1], "Dsus4");
+    assert_eq!(c_major.harmonize(&Harmony::quartal)[0], "C-F-B");
+    assert_eq!("7".parse::<Harmony>(), Ok(Harmony::sevenths));
+
+    let a_pentatonic =
+        Scale::from_type_and_root(Note { semitones: 9 }, ScaleType::minor_pentatonic);
+    assert_eq!(
+        a_pentatonic.harmonize(&Harmony::triads),
+        vec!["Am", "C", "Dsus4", "C/E", "Gsus4", "Am"]
+    );
+    assert_eq!(a_pentatonic.harmonize(&Harmony::sevenths)[0], "Am7");
+
+    /* no perfect fifth: a major third takes the #5 */
+    let whole_tone = Scale::from_type_and_root(Note { semitones: 0 }, ScaleType::whole_tone);
+    assert_eq!(whole_tone.harmonize(&Harmony::triads)[..2], ["C+", "D+"]);
+    let hirajoshi = Scale::from_type_and_root(Note { semitones: 0 }, ScaleType::hirajoshi);
+    assert_eq!(
+        hirajoshi.harmonize(&Harmony::triads),
+        vec!["Cm", "(D-G-Ab)", "Eb", "Gsus4", "Ab", "Cm"]
+    );
+    let prometheus = Scale::from_type_and_root(Note { semitones: 0 }, ScaleType::prometheus);
+    assert_eq!(prometheus.harmonize(&Harmony::triads)[2], "Am/E");
 }
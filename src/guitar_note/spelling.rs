@@ -0,0 +1,176 @@
+use super::note::Note;
+use std::fmt;
+
+/* Spelling
+ * A note name as letter plus accidentals, so that the same pitch class
+ * can be written as f#, gb or e## depending on key and context.
+ */
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Hash)]
+pub struct Spelling {
+    letter: i32,
+    accidentals: i32,
+}
+
+impl Spelling {
+    const LETTERS: [&'static str; 7] = ["c", "d", "e", "f", "g", "a", "b"];
+    const NATURALS: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+    /* (letter, accidentals) of the usual name of a major (minor) key
+     * on each pitch class, e.g. Db major but C# minor.
+     */
+    const MAJOR_ROOTS: [(i32, i32); 12] = [
+        (0, 0),
+        (1, -1),
+        (1, 0),
+        (2, -1),
+        (2, 0),
+        (3, 0),
+        (3, 1),
+        (4, 0),
+        (5, -1),
+        (5, 0),
+        (6, -1),
+        (6, 0),
+    ];
+    const MINOR_ROOTS: [(i32, i32); 12] = [
+        (0, 0),
+        (0, 1),
+        (1, 0),
+        (2, -1),
+        (2, 0),
+        (3, 0),
+        (3, 1),
+        (4, 0),
+        (4, 1),
+        (5, 0),
+        (6, -1),
+        (6, 0),
+    ];
+
+    pub fn new(letter: i32, note: Note) -> Spelling {
+        /* Spells the note with the given letter (0 is c, 6 is b),
+         * using as many accidentals as needed.
+         */
+        let letter = letter.rem_euclid(7);
+        let mut accidentals =
+            (note.pitch_class().semitones - Spelling::NATURALS[letter as usize]).rem_euclid(12);
+        if accidentals > 6 {
+            accidentals -= 12;
+        }
+        Spelling {
+            letter,
+            accidentals,
+        }
+    }
+    pub fn of_root(note: Note, minor: bool) -> Spelling {
+        let table = match minor {
+            true => &Spelling::MINOR_ROOTS,
+            false => &Spelling::MAJOR_ROOTS,
+        };
+        let (letter, accidentals) = table[note.pitch_class().semitones as usize];
+        Spelling {
+            letter,
+            accidentals,
+        }
+    }
+    pub fn candidates(note: Note) -> Vec<Spelling> {
+        /* All spellings with at most one accidental, e.g. g# and ab.
+         */
+        (0..7)
+            .map(|letter| Spelling::new(letter, note))
+            .filter(|s| s.accidentals.abs() <= 1)
+            .collect()
+    }
+    pub fn from_string(name: &str) -> Option<Spelling> {
+        /* Parses names such as c, db, f#, e## or bbb.
+         */
+        let name = name.to_lowercase();
+        let letter = Spelling::LETTERS.iter().position(|l| name.starts_with(l))? as i32;
+        let accidentals = &name[1..];
+        let n_sharps = accidentals.chars().filter(|c| *c == '#').count() as i32;
+        let n_flats = accidentals.chars().filter(|c| *c == 'b').count() as i32;
+        if (n_sharps + n_flats) as usize != accidentals.len() || (n_sharps > 0 && n_flats > 0) {
+            return None;
+        }
+        Some(Spelling {
+            letter,
+            accidentals: n_sharps - n_flats,
+        })
+    }
+    pub fn split_name(name: &str) -> Option<(Spelling, &str)> {
+        /* Splits a leading note name with at most one accidental from
+         * the rest, e.g. "Bb" and "13" in "Bb13".
+         */
+        let letter_len = name.chars().next()?.len_utf8();
+        let name_len = match name[letter_len..].chars().next() {
+            Some('#') | Some('b') => letter_len + 1,
+            _ => letter_len,
+        };
+        let spelling = Spelling::from_string(&name[..name_len])?;
+        Some((spelling, &name[name_len..]))
+    }
+
+    pub fn pitch_class(&self) -> Note {
+        Note {
+            semitones: Spelling::NATURALS[self.letter as usize] + self.accidentals,
+        }
+        .pitch_class()
+    }
+    pub fn letter(&self) -> i32 {
+        self.letter
+    }
+    pub fn accidentals(&self) -> i32 {
+        self.accidentals
+    }
+    pub fn above(&self, semitones: i32, degree: i32) -> Spelling {
+        /* The note the given number of semitones above, spelled as the
+         * given scale degree (1 is the unison, 3 the third, 9 the ninth).
+         */
+        Spelling::new(
+            self.letter + degree - 1,
+            self.pitch_class() + Note { semitones },
+        )
+    }
+    pub fn capitalized(&self) -> String {
+        /* Only the letter is upper case, so that b flat reads Bb.
+         */
+        let name = self.to_string();
+        format!("{}{}", name[..1].to_uppercase(), &name[1..])
+    }
+}
+impl fmt::Display for Spelling {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let accidental = match self.accidentals >= 0 {
+            true => "#",
+            false => "b",
+        };
+        write!(
+            f,
+            "{}{}",
+            Spelling::LETTERS[self.letter as usize],
+            accidental.repeat(self.accidentals.unsigned_abs() as usize)
+        )
+    }
+}
+#[test]
+fn test_spelling() {
+    let g_sharp = Note { semitones: 8 };
+    let names = Spelling::candidates(g_sharp)
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["g#", "ab"]);
+    assert_eq!(Spelling::of_root(g_sharp, false).capitalized(), "Ab");
+    assert_eq!(Spelling::of_root(g_sharp, true).capitalized(), "G#");
+
+    let d_sharp = Spelling::from_string("d#").unwrap();
+    assert_eq!(d_sharp.above(4, 3).to_string(), "f##");
+    assert_eq!(d_sharp.above(7, 5).to_string(), "a#");
+    let d_flat = Spelling::from_string("Db").unwrap();
+    assert_eq!(d_flat.above(4, 3).to_string(), "f");
+    assert_eq!(d_flat.above(7, 5).to_string(), "ab");
+    assert_eq!(
+        Spelling::from_string("ebb").unwrap().pitch_class(),
+        Note { semitones: 2 }
+    );
+    assert_eq!(Spelling::from_string("x"), None);
+}
@@ -0,0 +1,619 @@
+use super::chord::Chord;
+use super::fretboard::FretWindow;
+use super::note::Note;
+use super::tuning::Tuning;
+use itertools::Itertools;
+use std::cmp;
+
+/* ASCII tablature
+ * Reads tabs as they are found on the web, one line per string with
+ * the highest string on top:
+ *
+ *     e|-----0---------|---------------|
+ *     B|---1---1-------|-----3---------|
+ *     G|-2-------2-----|---0---0h2-----|
+ *     D|---------------|-0-------------|
+ *     A|-3-------------|---------------|
+ *     E|---------------|-3-------------|
+ *
+ * Staves with another number of lines than the tuning has strings are
+ * skipped, as is any text between them.
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Technique {
+    hammer_on,
+    pull_off,
+    bend,
+    release,
+    slide_up,
+    slide_down,
+    slide,
+}
+impl Technique {
+    const SYMBOLS: [(char, Technique); 7] = [
+        ('h', Technique::hammer_on),
+        ('p', Technique::pull_off),
+        ('b', Technique::bend),
+        ('r', Technique::release),
+        ('/', Technique::slide_up),
+        ('\\', Technique::slide_down),
+        ('s', Technique::slide),
+    ];
+    fn from_symbol(symbol: char) -> Option<Technique> {
+        Technique::SYMBOLS
+            .iter()
+            .find(|(c, _)| *c == symbol)
+            .map(|(_, t)| *t)
+    }
+    pub fn symbol(&self) -> char {
+        Technique::SYMBOLS
+            .iter()
+            .find(|(_, t)| t == self)
+            .map(|(c, _)| *c)
+            .unwrap()
+    }
+}
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TabNote {
+    /* None for a dead note (x) */
+    pub fret: Option<i32>,
+    /* how the note is reached from the one before, e.g. 5h7 */
+    pub technique: Option<Technique>,
+}
+#[derive(Clone, Debug)]
+pub struct TabEvent {
+    /* counted from 1 */
+    pub bar: usize,
+    /* the column across all staves, the tab's measure of time */
+    pub column: usize,
+    /* one per string, lowest string first */
+    pub notes: Vec<Option<TabNote>>,
+}
+impl TabEvent {
+    pub fn new(bar: usize, frets: &[Option<i32>]) -> TabEvent {
+        /* Plain notes, None for strings that aren't played. */
+        TabEvent {
+            bar,
+            column: 0,
+            notes: frets
+                .iter()
+                .map(|f| {
+                    f.map(|f| TabNote {
+                        fret: Some(f),
+                        technique: None,
+                    })
+                })
+                .collect(),
+        }
+    }
+    pub fn frets(&self) -> Vec<Option<i32>> {
+        /* the sounding frets, dead notes don't sound */
+        self.notes.iter().map(|n| n.and_then(|n| n.fret)).collect()
+    }
+    pub fn chord(&self, tuning: &Tuning) -> Option<Chord> {
+        let notes = tuning
+            .tune_frets(&self.frets())
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        TabEvent::name(&notes)
+    }
+    fn name(notes: &[Note]) -> Option<Chord> {
+        /* A melody's steps are no chord, so a bare interval such as
+         * the E2 of e12 h14 is left unnamed; power chords are named.
+         */
+        Chord::find_slash_chords(notes)
+            .into_iter()
+            .next()
+            .filter(|c| !c.is_interval())
+    }
+}
+pub struct Tab {
+    pub events: Vec<TabEvent>,
+}
+impl Tab {
+    const STAFF_CHARS: &'static str = "-0123456789hpbr/\\sx|~()<>^.*";
+
+    fn staff_line(line: &str) -> Option<&str> {
+        /* The part of a line after the string name, e.g. "---0---|"
+         * of "e|---0---|", or None for a line of text.
+         */
+        let line = line.trim();
+        let name_len = line
+            .char_indices()
+            .take_while(|(i, c)| *i < 2 && (c.is_ascii_alphabetic() || *c == '#'))
+            .count();
+        let rest = line[name_len..].trim_start();
+        let rest = rest
+            .strip_prefix('|')
+            .or(rest.strip_prefix(':'))
+            .unwrap_or(rest);
+        if rest.matches('-').count() < 2 || !rest.chars().all(|c| Tab::STAFF_CHARS.contains(c)) {
+            return None;
+        }
+        Some(rest)
+    }
+    fn read_line(line: &str) -> Vec<(usize, TabNote)> {
+        /* The notes of one string with their columns. A fret takes the
+         * column of its first digit, e.g. the 12 of "-12-".
+         */
+        let chars = line.chars().collect::<Vec<_>>();
+        let mut notes = vec![];
+        let mut col = 0;
+        while col < chars.len() {
+            let technique = match col {
+                0 => None,
+                _ => Technique::from_symbol(chars[col - 1]),
+            };
+            if chars[col] == 'x' {
+                notes.push((
+                    col,
+                    TabNote {
+                        fret: None,
+                        technique: None,
+                    },
+                ));
+            } else if chars[col].is_ascii_digit() {
+                let digits = chars[col..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect::<String>();
+                notes.push((
+                    col,
+                    TabNote {
+                        fret: digits.parse::<i32>().ok(),
+                        technique,
+                    },
+                ));
+                col += digits.len();
+                continue;
+            }
+            col += 1;
+        }
+        notes
+    }
+    pub fn parse(text: &str, n_strings: usize) -> Option<Tab> {
+        let mut staves: Vec<Vec<&str>> = vec![];
+        let mut staff = vec![];
+        for line in text.lines().chain(std::iter::once("")) {
+            match Tab::staff_line(line) {
+                Some(content) => staff.push(content),
+                None => {
+                    if staff.len() == n_strings {
+                        staves.push(staff);
+                    }
+                    staff = vec![];
+                }
+            }
+        }
+        let mut events: Vec<TabEvent> = vec![];
+        let mut bar = 1;
+        let mut offset = 0;
+        for staff in staves {
+            let lines = staff
+                .iter()
+                .map(|l| l.chars().collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let width = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+            let mut staff_events: Vec<TabEvent> = vec![];
+            /* the highest string is on top */
+            for (line_idx, line) in staff.iter().enumerate() {
+                let string_idx = n_strings - 1 - line_idx;
+                for (col, note) in Tab::read_line(line) {
+                    let event = match staff_events.iter().position(|e| e.column == offset + col) {
+                        Some(pos) => &mut staff_events[pos],
+                        None => {
+                            staff_events.push(TabEvent {
+                                bar: 0,
+                                column: offset + col,
+                                notes: vec![None; n_strings],
+                            });
+                            staff_events.last_mut().unwrap()
+                        }
+                    };
+                    event.notes[string_idx] = Some(note);
+                }
+            }
+            staff_events.sort_by_key(|e| e.column);
+            /* a bar line runs through all strings, bars without notes
+             * (e.g. before the first bar line) aren't counted
+             */
+            let bar_lines = (0..width)
+                .filter(|col| lines.iter().all(|l| l.get(*col) == Some(&'|')))
+                .collect::<Vec<_>>();
+            let mut segments = staff_events
+                .iter()
+                .map(|e| {
+                    bar_lines
+                        .iter()
+                        .filter(|col| offset + **col < e.column)
+                        .count()
+                })
+                .collect::<Vec<_>>();
+            let bars = segments.iter().cloned().unique().collect::<Vec<_>>();
+            for (event, segment) in staff_events.iter_mut().zip(segments.drain(..)) {
+                event.bar = bar + bars.iter().position(|b| *b == segment).unwrap();
+            }
+            bar += bars.len();
+            offset += width;
+            events.extend(staff_events);
+        }
+        if events.is_empty() {
+            return None;
+        }
+        Some(Tab { events })
+    }
+    pub fn chord_chart(&self, tuning: &Tuning) -> Vec<Vec<Chord>> {
+        /* The chords of each bar in order, one per change. Double
+         * stops don't count, a bar without chords of three notes or
+         * more, e.g. a picked arpeggio, is named by all of its notes
+         * together.
+         */
+        let n_bars = self.events.iter().map(|e| e.bar).max().unwrap_or(0);
+        let mut chart = vec![];
+        for bar in 1..=n_bars {
+            let events = self
+                .events
+                .iter()
+                .filter(|e| e.bar == bar)
+                .collect::<Vec<_>>();
+            let mut chords: Vec<Chord> = vec![];
+            let full_chords = events
+                .iter()
+                .filter(|e| e.frets().iter().filter(|f| f.is_some()).count() > 2)
+                .filter_map(|e| e.chord(tuning));
+            for chord in full_chords {
+                if chords.last().map(|c| c.to_string()) != Some(chord.to_string()) {
+                    chords.push(chord);
+                }
+            }
+            if chords.is_empty() {
+                let notes = events
+                    .iter()
+                    .flat_map(|e| tuning.tune_frets(&e.frets()))
+                    .flatten()
+                    .collect::<Vec<Note>>();
+                chords.extend(TabEvent::name(&notes));
+            }
+            chart.push(chords);
+        }
+        chart
+    }
+    fn token(note: &Option<TabNote>) -> String {
+        match note {
+            Some(TabNote {
+                fret: Some(fret), ..
+            }) => fret.to_string(),
+            Some(TabNote { fret: None, .. }) => String::from("x"),
+            None => String::from(""),
+        }
+    }
+    pub fn render(&self, string_names: &[String], bars_per_staff: usize) -> String {
+        /* Writes the events as ASCII tab, the highest string on top.
+         * Notes of an event share a column as wide as its widest
+         * fret, e.g. 12 and 3 are written as "12" and "3-". A
+         * technique goes right before its fret, as in 5h7.
+         */
+        let n_strings = string_names.len();
+        let name_width = string_names.iter().map(|n| n.len()).max().unwrap_or(0);
+        let n_bars = self.events.iter().map(|e| e.bar).max().unwrap_or(0);
+        let mut staves = vec![];
+        let bars = (1..=n_bars).collect::<Vec<_>>();
+        for staff_bars in bars.chunks(cmp::max(bars_per_staff, 1)) {
+            let mut lines = string_names
+                .iter()
+                .map(|n| format!("{:width$}|", n, width = name_width))
+                .collect::<Vec<_>>();
+            for bar in staff_bars {
+                for line in lines.iter_mut() {
+                    line.push('-');
+                }
+                for event in self.events.iter().filter(|e| e.bar == *bar) {
+                    let tokens = event.notes.iter().map(Tab::token).collect::<Vec<_>>();
+                    let width = tokens.iter().map(|t| t.len()).max().unwrap_or(0);
+                    for ((line, token), note) in
+                        lines.iter_mut().zip(tokens.iter()).zip(&event.notes)
+                    {
+                        if let Some(technique) = note.and_then(|n| n.technique) {
+                            line.pop();
+                            line.push(technique.symbol());
+                        }
+                        line.push_str(&format!("{:-<width$}-", token, width = width));
+                    }
+                }
+                for line in lines.iter_mut() {
+                    line.push('|');
+                }
+            }
+            lines.reverse();
+            staves.push(lines.join("\n"));
+        }
+        if n_strings == 0 {
+            return String::from("");
+        }
+        staves.join("\n\n")
+    }
+    pub fn exercise(
+        pitch_classes: &[Note],
+        tuning: &Tuning,
+        window: &FretWindow,
+        notes_per_bar: usize,
+    ) -> Tab {
+        /* The notes in one position of the neck, up from the lowest
+         * string and back down, e.g. a scale or an arpeggio. A note
+         * already reached on a lower string isn't repeated.
+         */
+        let pitch_classes = pitch_classes
+            .iter()
+            .map(|n| n.pitch_class())
+            .collect::<Vec<_>>();
+        let n_strings = tuning.get_basenotes().len();
+        let mut ascending: Vec<(usize, i32)> = vec![];
+        let mut highest: Option<Note> = None;
+        for string_idx in 0..n_strings {
+            for fret in window.frets() {
+                let pitch = match tuning.fret_pitch(string_idx, fret) {
+                    Some(pitch) => pitch,
+                    None => continue,
+                };
+                if pitch_classes.contains(&pitch.pitch_class())
+                    && highest.map(|h| pitch > h).unwrap_or(true)
+                {
+                    ascending.push((string_idx, fret));
+                    highest = Some(pitch);
+                }
+            }
+        }
+        let descending = ascending.iter().rev().skip(1);
+        let events = ascending
+            .iter()
+            .chain(descending)
+            .enumerate()
+            .map(|(idx, (string_idx, fret))| {
+                let mut frets = vec![None; n_strings];
+                frets[*string_idx] = Some(*fret);
+                TabEvent::new(idx / cmp::max(notes_per_bar, 1) + 1, &frets)
+            })
+            .collect();
+        Tab { events }
+    }
+    fn movement(from: (usize, i32), to: (usize, i32)) -> i32 {
+        /* Shifting the hand costs twice as much as changing strings,
+         * open strings leave the hand where it is.
+         */
+        let strings = (from.0 as i32 - to.0 as i32).abs();
+        match from.1 == 0 || to.1 == 0 {
+            true => strings,
+            false => 2 * (from.1 - to.1).abs() + strings,
+        }
+    }
+    pub fn fingering(notes: &[(usize, Note)], tuning: &Tuning, window: &FretWindow) -> Tab {
+        /* A single line of (bar, note) in the window with the least
+         * movement of the hand and, among those, the lowest frets.
+         * Notes that can't be played in the window are left out.
+         */
+        let n_strings = tuning.get_basenotes().len();
+        let positions = notes
+            .iter()
+            .map(|(bar, note)| {
+                let places = (0..n_strings)
+                    .flat_map(|idx| window.frets().map(move |fret| (idx, fret)))
+                    .filter(|(idx, fret)| tuning.fret_pitch(*idx, *fret) == Some(*note))
+                    .collect::<Vec<_>>();
+                (*bar, places)
+            })
+            .filter(|(_, places)| !places.is_empty())
+            .collect::<Vec<_>>();
+        /* the cheapest way to each place of a note and where it came from */
+        let mut costs: Vec<Vec<(i32, usize)>> = vec![];
+        for (idx, (_, places)) in positions.iter().enumerate() {
+            let step = places
+                .iter()
+                .map(|place| match idx {
+                    0 => (place.1, 0),
+                    _ => positions[idx - 1]
+                        .1
+                        .iter()
+                        .zip(costs[idx - 1].iter())
+                        .enumerate()
+                        .map(|(from, (previous, (cost, _)))| {
+                            (cost + 4 * Tab::movement(*previous, *place) + place.1, from)
+                        })
+                        .min()
+                        .unwrap(),
+                })
+                .collect();
+            costs.push(step);
+        }
+        let mut chosen = vec![];
+        if let Some(last) = costs.last() {
+            let mut place = (0..last.len()).min_by_key(|p| last[*p]).unwrap();
+            for idx in (0..positions.len()).rev() {
+                chosen.push(positions[idx].1[place]);
+                place = costs[idx][place].1;
+            }
+            chosen.reverse();
+        }
+        let events = positions
+            .iter()
+            .zip(chosen)
+            .map(|((bar, _), (string_idx, fret))| {
+                let mut frets = vec![None; n_strings];
+                frets[string_idx] = Some(fret);
+                TabEvent::new(*bar, &frets)
+            })
+            .collect();
+        Tab { events }
+    }
+}
+#[test]
+fn test_parse() {
+    let text = "Intro
+e|-----0---------|---------------|
+B|---1---1-------|-----3---------|
+G|-2-------2-----|---0---0h2-----|
+D|---------------|-0-------------|
+A|-3-------------|---------------|
+E|---------------|-3-------------|
+
+e|--------12p10-|
+B|--------------|
+G|--7b9---------|
+D|--7-----x-----|
+A|--5-----------|
+E|--------------|
+";
+    let tab = Tab::parse(text, 6).unwrap();
+    assert_eq!(tab.events.len(), 14);
+    assert_eq!(tab.events[0].bar, 1);
+    assert_eq!(
+        tab.events[0].frets(),
+        vec![None, Some(3), None, Some(2), None, None]
+    );
+    let hammer_on = tab
+        .events
+        .iter()
+        .find(|e| e.notes[3].and_then(|n| n.technique).is_some());
+    assert_eq!(
+        hammer_on.unwrap().notes[3],
+        Some(TabNote {
+            fret: Some(2),
+            technique: Some(Technique::hammer_on)
+        })
+    );
+    let power_chord = tab.events.iter().find(|e| e.bar == 3).unwrap();
+    assert_eq!(
+        power_chord.frets(),
+        vec![None, Some(5), Some(7), Some(7), None, None]
+    );
+    let pull_off = tab.events.last().unwrap();
+    assert_eq!(pull_off.notes[5].unwrap().fret, Some(10));
+    assert_eq!(
+        pull_off.notes[5].unwrap().technique,
+        Some(Technique::pull_off)
+    );
+    assert!(Tab::parse("no tab here", 6).is_none());
+}
+#[test]
+fn test_chord_chart() {
+    let text = "
+e|-0-----|-2-----|-3-----|
+B|-1-----|-3-----|-0-----|
+G|-0-----|-2-----|-0-----|
+D|-2-----|-0-----|-0-----|
+A|-3-----|-------|-2-----|
+E|-------|-------|-3-----|
+";
+    let tuning = Tuning::from_name("eadgbe").unwrap();
+    let tab = Tab::parse(text, 6).unwrap();
+    let chart = tab
+        .chord_chart(&tuning)
+        .iter()
+        .map(|bar| {
+            bar.iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(chart, vec!["C", "D", "G"]);
+
+    /* a bar of melody is no chord */
+    let melody = Tab::parse("e|-12h14p12-|", 1).unwrap();
+    let one_string = Tuning::from_notes(vec![Note { semitones: 28 }]);
+    assert!(melody.chord_chart(&one_string)[0].is_empty());
+    assert!(melody.events.iter().all(|e| e.chord(&one_string).is_none()));
+}
+#[test]
+fn test_render() {
+    let names = ["e", "a", "d", "g", "b", "e"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+    let mut hammer_on = TabEvent::new(1, &[None; 6]);
+    hammer_on.notes[3] = Some(TabNote {
+        fret: Some(2),
+        technique: Some(Technique::hammer_on),
+    });
+    let tab = Tab {
+        events: vec![
+            TabEvent::new(1, &[None, Some(3), Some(2), Some(0), Some(1), Some(0)]),
+            hammer_on,
+            TabEvent::new(1, &[Some(12), None, None, None, None, None]),
+            TabEvent::new(2, &[None, None, None, None, None, Some(3)]),
+        ],
+    };
+    assert_eq!(
+        tab.render(&names, 4),
+        "e|-0------|-3-|
+b|-1------|---|
+g|-0h2----|---|
+d|-2------|---|
+a|-3------|---|
+e|-----12-|---|"
+    );
+    /* what is written can be read */
+    let text = tab.render(&names, 1);
+    let read = Tab::parse(&text, 6).unwrap();
+    assert_eq!(read.events.len(), 4);
+    assert_eq!(read.events[1].notes[3], tab.events[1].notes[3]);
+    assert_eq!(read.events[2].frets()[0], Some(12));
+    assert_eq!(read.events[3].bar, 2);
+}
+#[test]
+fn test_exercise() {
+    let tuning = Tuning::from_name("eadgbe").unwrap();
+    let window = FretWindow::from_string("5-8", 24).unwrap();
+    // A minor pentatonic in fifth position
+    let notes = [9, 0, 2, 4, 7]
+        .iter()
+        .map(|s| Note { semitones: *s })
+        .collect::<Vec<_>>();
+    let tab = Tab::exercise(&notes, &tuning, &window, 4);
+    let frets = tab
+        .events
+        .iter()
+        .map(|e| e.frets().iter().filter_map(|f| *f).next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(frets[..12], [5, 8, 5, 7, 5, 7, 5, 7, 5, 8, 5, 8]);
+    assert_eq!(tab.events.len(), 23);
+    assert_eq!(tab.events[4].bar, 2);
+}
+#[test]
+fn test_fingering() {
+    let tuning = Tuning::from_name("eadgbe").unwrap();
+    /* a2 c3 d3 e3, then a3 c4 d4 e4 an octave up */
+    let notes = [9, 12, 14, 16, 21, 24, 26, 28]
+        .iter()
+        .map(|s| (1, Note { semitones: *s }))
+        .collect::<Vec<_>>();
+    let tab = Tab::fingering(&notes, &tuning, &FretWindow::new(24));
+    let places = tab
+        .events
+        .iter()
+        .map(|e| {
+            let string_idx = e.frets().iter().position(|f| f.is_some()).unwrap();
+            (string_idx, e.frets()[string_idx].unwrap())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        places,
+        vec![
+            (1, 0),
+            (1, 3),
+            (2, 0),
+            (2, 2),
+            (3, 2),
+            (4, 1),
+            (4, 3),
+            (5, 0)
+        ]
+    );
+    let high = Tab::fingering(
+        &notes,
+        &tuning,
+        &FretWindow::from_string("5-8", 24).unwrap(),
+    );
+    assert_eq!(high.events[0].frets()[0], Some(5));
+    assert_eq!(high.events.len(), 8);
+}
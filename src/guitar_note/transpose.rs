@@ -0,0 +1,107 @@
+use super::chord::Chord;
+use super::note::Note;
+use super::scale::{Scale, ScaleType};
+use super::spelling::Spelling;
+
+/* Transposition
+ * Chords are moved by an interval and named in the key they end up in.
+ * Roots in the key keep its spelling, the others are spelled as the
+ * altered degrees b2, b3, #4, b6 and b7, so that a song in Eb major
+ * gets a Db rather than a C#.
+ */
+pub struct Transposition {
+    pub semitones: i32,
+    /* the key after transposition */
+    pub key: Scale,
+}
+
+impl Transposition {
+    /* the degree a root the given number of semitones above the tonic
+     * is spelled as
+     */
+    const DEGREES: [i32; 12] = [1, 2, 2, 3, 3, 4, 4, 5, 6, 6, 7, 7];
+
+    fn shortest(semitones: i32) -> i32 {
+        /* between a fourth down and a tritone up */
+        let semitones = semitones.rem_euclid(12);
+        match semitones > 6 {
+            true => semitones - 12,
+            false => semitones,
+        }
+    }
+    pub fn new(key: &Scale, semitones: i32) -> Transposition {
+        let tonic = key.get_notes()[0] + Note { semitones };
+        Transposition {
+            semitones,
+            key: Scale::from_type_and_root(tonic.pitch_class(), key.scale_type.clone()),
+        }
+    }
+    pub fn to_key(from: &Scale, to: Scale) -> Transposition {
+        let semitones = (to.get_notes()[0] - from.get_notes()[0]).semitones;
+        Transposition {
+            semitones: Transposition::shortest(semitones),
+            key: to,
+        }
+    }
+    pub fn key_symbol(&self) -> String {
+        /* the key as in a chord sheet, e.g. Bb, C#m or D dorian */
+        let tonic = self.key.spelling()[0].capitalized();
+        match self.key.scale_type {
+            ScaleType::major => tonic,
+            ScaleType::minor => format!("{}m", tonic),
+            _ => format!("{} {:?}", tonic, self.key.scale_type),
+        }
+    }
+    pub fn spell(&self, note: Note) -> Spelling {
+        let key_spelling = self.key.spelling();
+        if let Some(spelling) = key_spelling
+            .iter()
+            .find(|s| s.pitch_class() == note.pitch_class())
+        {
+            return *spelling;
+        }
+        let tonic = key_spelling[0];
+        let interval = (note - tonic.pitch_class()).pitch_class().semitones;
+        let spelling = tonic.above(interval, Transposition::DEGREES[interval as usize]);
+        /* no double flats, e.g. the b3 of Db major is written E */
+        match spelling.accidentals().abs() > 1 {
+            true => Spelling::of_root(note, false),
+            false => spelling,
+        }
+    }
+    pub fn chord(&self, chord: &Chord) -> Chord {
+        chord.transposed(self.semitones)
+    }
+    pub fn chord_name(&self, chord: &Chord) -> String {
+        let chord = self.chord(chord);
+        chord.to_string_with_root(&self.spell(chord.get_notes()[0]))
+    }
+}
+#[test]
+fn test_transpose() {
+    let key = |root: i32, scale_type: ScaleType| {
+        Scale::from_type_and_root(Note { semitones: root }, scale_type)
+    };
+    let progression = ["Am", "F", "C", "G", "E7/G#"]
+        .iter()
+        .map(|s| Chord::from_symbol(s).unwrap())
+        .collect::<Vec<_>>();
+    let names = |t: &Transposition| {
+        progression
+            .iter()
+            .map(|c| t.chord_name(c))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let a_minor = key(9, ScaleType::minor);
+    let up = Transposition::new(&a_minor, 1);
+    assert_eq!(up.key_symbol(), "Bbm");
+    assert_eq!(names(&up), "Bbm Gb Db Ab F7/A");
+    let to_c_sharp = Transposition::to_key(&a_minor, key(1, ScaleType::minor));
+    assert_eq!(to_c_sharp.semitones, 4);
+    assert_eq!(names(&to_c_sharp), "C#m A E B G#7/B#");
+    /* borrowed chords are spelled as altered degrees */
+    let eb_major = Transposition::new(&key(0, ScaleType::major), 3);
+    let borrowed = Chord::from_symbol("Bb").unwrap();
+    assert_eq!(eb_major.chord_name(&borrowed), "Db");
+}
@@ -1,4 +1,6 @@
 use super::note::Note;
+use super::pitch::Pitch;
+use super::spelling::Spelling;
 #[derive(Clone, enum_utils::FromStr, enum_utils::IterVariants, Debug)]
 enum TuningType {
     eadgbe,
@@ -13,11 +15,31 @@ enum TuningType {
     openg,
     opena,
     openc,
+    bass,
+    bass5,
+    bass6,
+    sevenstring,
+    eightstring,
+    twelvestring,
+    ukulele,
+    baritoneukulele,
+    mandolin,
+    banjo,
 }
+#[derive(Clone)]
 pub struct Tuning {
-    tuning_type: TuningType,
     note_strings: Vec<String>,
     base_notes: Vec<Note>,
+    /* Short strings like the fifth string of a banjo start at a later
+     * fret; their base note is that of a full length string.
+     */
+    first_frets: Vec<i32>,
+    /* Further strings of a course, relative to its base note, e.g. the
+     * octave strings of a twelve string guitar.
+     */
+    courses: Vec<Vec<i32>>,
+    /* Fret of the capo, base notes are those of the capoed strings */
+    capo: i32,
 }
 impl Tuning {
     /* semitone steps relative to lowest C */
@@ -117,11 +139,62 @@ impl Tuning {
         23, /*b*/
         28, /*e*/
     ];
+    const BASS: [i32; 4] = [-8 /*e*/, -3 /*a*/, 2 /*d*/, 7 /*g*/];
+    const BASS5: [i32; 5] = [
+        -13, /*b*/
+        -8,  /*e*/
+        -3,  /*a*/
+        2,   /*d*/
+        7,   /*g*/
+    ];
+    const BASS6: [i32; 6] = [
+        -13, /*b*/
+        -8,  /*e*/
+        -3,  /*a*/
+        2,   /*d*/
+        7,   /*g*/
+        12,  /*c*/
+    ];
+    const SEVENSTRING: [i32; 7] = [
+        -1, /*b*/
+        4,  /*e*/
+        9,  /*a*/
+        14, /*d*/
+        19, /*g*/
+        23, /*b*/
+        28, /*e*/
+    ];
+    const EIGHTSTRING: [i32; 8] = [
+        -6, /*f#*/
+        -1, /*b*/
+        4,  /*e*/
+        9,  /*a*/
+        14, /*d*/
+        19, /*g*/
+        23, /*b*/
+        28, /*e*/
+    ];
+    /* re-entrant: the g string is tuned above the c string */
+    const UKULELE: [i32; 4] = [31 /*g*/, 24 /*c*/, 28 /*e*/, 33 /*a*/];
+    const BARITONEUKULELE: [i32; 4] = [14 /*d*/, 19 /*g*/, 23 /*b*/, 28 /*e*/];
+    const MANDOLIN: [i32; 4] = [19 /*g*/, 26 /*d*/, 33 /*a*/, 40 /*e*/];
+    /* open G, the short fifth string (g) sounds at the fifth fret of a d string */
+    const BANJO: [i32; 5] = [
+        26, /*(g)*/
+        14, /*d*/
+        19, /*g*/
+        23, /*b*/
+        26, /*d*/
+    ];
+    const BANJO_FIRST_FRETS: [i32; 5] = [5, 0, 0, 0, 0];
+    const TWELVESTRING_COURSES: [i32; 6] = [12, 12, 12, 12, 0, 0];
+    const MANDOLIN_COURSES: [i32; 4] = [0, 0, 0, 0];
+
     pub fn get_basenotes<'a>(&'a self) -> &'a Vec<Note> {
         return &self.base_notes;
     }
-    pub fn to_string(&self) -> String {
-        return format!("{:?}", self.tuning_type);
+    pub fn get_string_names(&self) -> &Vec<String> {
+        &self.note_strings
     }
     pub fn from_name(name: &str) -> Option<Tuning> {
         let type_ = name.parse::<TuningType>();
@@ -131,7 +204,7 @@ impl Tuning {
         };
     }
     fn from_type(tuning_type: TuningType) -> Tuning {
-        let notes = match tuning_type {
+        let notes: &[i32] = match tuning_type {
             TuningType::dropd => &Tuning::DROPD,
             TuningType::doubledropd => &Tuning::DOUBLEDROPD,
             TuningType::dadgad => &Tuning::DADGAD,
@@ -143,46 +216,198 @@ impl Tuning {
             TuningType::opend => &Tuning::OPEND,
             TuningType::opene => &Tuning::OPENE,
             TuningType::openg => &Tuning::OPENG,
-            TuningType::eadgbe => &Tuning::EADGBE,
+            TuningType::eadgbe | TuningType::twelvestring => &Tuning::EADGBE,
+            TuningType::bass => &Tuning::BASS,
+            TuningType::bass5 => &Tuning::BASS5,
+            TuningType::bass6 => &Tuning::BASS6,
+            TuningType::sevenstring => &Tuning::SEVENSTRING,
+            TuningType::eightstring => &Tuning::EIGHTSTRING,
+            TuningType::ukulele => &Tuning::UKULELE,
+            TuningType::baritoneukulele => &Tuning::BARITONEUKULELE,
+            TuningType::mandolin => &Tuning::MANDOLIN,
+            TuningType::banjo => &Tuning::BANJO,
         };
-        let note_str_it = notes.iter().map(|i| {
-            Note {
-                semitones: i.clone(),
-            }
-            .to_string()
-            .to_owned()
-        });
         let base_notes = notes
             .iter()
             .map(|s| Note { semitones: *s })
             .collect::<Vec<_>>();
-
+        let tuning = Tuning::from_notes(base_notes);
+        match tuning_type {
+            TuningType::twelvestring => tuning.with_courses(&Tuning::TWELVESTRING_COURSES),
+            TuningType::mandolin => tuning.with_courses(&Tuning::MANDOLIN_COURSES),
+            TuningType::banjo => tuning.with_first_frets(&Tuning::BANJO_FIRST_FRETS),
+            _ => tuning,
+        }
+    }
+    fn with_courses(mut self, course_offsets: &[i32]) -> Tuning {
+        /* Pairs every string with a second one, course_offsets
+         * semitones above (0 for unison courses).
+         */
+        self.courses = course_offsets.iter().map(|o| vec![*o]).collect();
+        self
+    }
+    fn with_first_frets(mut self, first_frets: &[i32]) -> Tuning {
+        self.first_frets = first_frets.to_vec();
+        self.note_strings = self
+            .base_notes
+            .iter()
+            .zip(first_frets)
+            .map(|(n, f)| (*n + Note { semitones: *f }).to_string().to_owned())
+            .collect();
+        self
+    }
+    pub fn get_first_frets(&self) -> &Vec<i32> {
+        &self.first_frets
+    }
+    pub fn with_capo(mut self, capo: i32) -> Tuning {
+        /* Frets are counted from the capo from now on, so that tab
+         * written for a capoed guitar sounds in concert pitch. A short
+         * string is assumed to be capoed (or retuned) alike.
+         */
+        let shift = Note {
+            semitones: capo - self.capo,
+        };
+        self.base_notes = self.base_notes.iter().map(|n| *n + shift).collect();
+        self.capo = capo;
+        self
+    }
+    pub fn get_capo(&self) -> i32 {
+        self.capo
+    }
+    pub fn fret_pitch(&self, string_idx: usize, fret: i32) -> Option<Note> {
+        /* Pitch of a fret as written in tab. On a short string, 0 is
+         * the open string, which lies on a later fret of the neck.
+         */
+        let first_fret = self.first_frets[string_idx];
+        let neck_fret = match fret {
+            0 => first_fret,
+            f if f < 0 || f < first_fret => return None,
+            f => f,
+        };
+        Some(
+            self.base_notes[string_idx]
+                + Note {
+                    semitones: neck_fret,
+                },
+        )
+    }
+    pub fn from_notes(base_notes: Vec<Note>) -> Tuning {
+        /* base_notes from the lowest to the highest string.
+         */
+        let note_strings = base_notes
+            .iter()
+            .map(|n| n.to_string().to_owned())
+            .collect::<Vec<_>>();
         return Tuning {
-            tuning_type: tuning_type,
-            note_strings: note_str_it.collect::<Vec<_>>(),
+            note_strings,
+            first_frets: vec![0; base_notes.len()],
+            courses: vec![vec![]; base_notes.len()],
             base_notes: base_notes,
+            capo: 0,
         };
     }
-    pub fn tune(&self, strings: &Vec<&str>, notes: &Vec<Note>) -> Vec<Option<Note>> {
-        let mut result: Vec<Option<Note>> = vec![];
+    pub fn from_string(spec: &str) -> Option<Tuning> {
+        /* Reads a list of string pitches from low to high, either with
+         * octaves ("C2 G2 C3 F3 C4 E4") or without ("D A D F# A D").
+         * Without octaves, the lowest string is put close to the low E
+         * of a guitar and every further string above its neighbour.
+         */
+        let names = spec
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            return None;
+        }
+        let pitches = names
+            .iter()
+            .map(|n| Pitch::from_string(n))
+            .collect::<Option<Vec<_>>>();
+        let base_notes = match pitches {
+            Some(pitches) => pitches.iter().map(|p| p.to_note()).collect::<Vec<_>>(),
+            None => {
+                let spellings = names
+                    .iter()
+                    .map(|n| Spelling::from_string(n))
+                    .collect::<Option<Vec<_>>>()?;
+                let low_e = Tuning::EADGBE[0];
+                let first = spellings[0].pitch_class().semitones;
+                let mut previous = first + 12 * ((low_e - first) as f64 / 12.0).round() as i32;
+                let mut notes = vec![Note {
+                    semitones: previous,
+                }];
+                for spelling in spellings.iter().skip(1) {
+                    let step = (spelling.pitch_class().semitones - previous).rem_euclid(12);
+                    previous += if step == 0 { 12 } else { step };
+                    notes.push(Note {
+                        semitones: previous,
+                    });
+                }
+                notes
+            }
+        };
+        Some(Tuning::from_notes(base_notes))
+    }
+    pub fn string_indices(&self, strings: &Vec<&str>) -> Vec<Option<usize>> {
+        /* Strings of the same name are assigned from low to high,
+         * e.g. the second "e" in "e a d g b e" is the high e string.
+         */
+        let mut result: Vec<Option<usize>> = vec![];
         let mut lower_string_idx = 0;
-        for (s, n) in strings.iter().zip(notes) {
+        for s in strings.iter() {
             let opt_pos = self
                 .note_strings
                 .iter()
                 .skip(lower_string_idx)
                 .position(|gs| gs == s);
             if let Some(pos) = opt_pos {
-                result.push(Some(Note {
-                    semitones: n.semitones + self.base_notes[pos + lower_string_idx].semitones,
-                }));
-                lower_string_idx = pos + 1;
+                result.push(Some(pos + lower_string_idx));
+                lower_string_idx = pos + lower_string_idx + 1;
             } else {
                 result.push(None);
             }
         }
         return result;
     }
+    pub fn tune(&self, strings: &Vec<&str>, notes: &Vec<Note>) -> Vec<Option<Note>> {
+        self.string_indices(strings)
+            .iter()
+            .zip(notes)
+            .map(|(opt_idx, n)| opt_idx.and_then(|idx| self.fret_pitch(idx, n.semitones)))
+            .collect()
+    }
+    pub fn tune_frets(&self, frets: &[Option<i32>]) -> Vec<Option<Note>> {
+        /* One fret (or None for a muted string) per string. Every
+         * string is named in order, so tune puts each fret on its own
+         * string; muted strings are tuned as open and dropped again.
+         */
+        let strings = self.note_strings.iter().map(|s| &s[..]).collect::<Vec<_>>();
+        let offsets = frets
+            .iter()
+            .map(|f| Note {
+                semitones: f.unwrap_or(0),
+            })
+            .collect::<Vec<_>>();
+        self.tune(&strings, &offsets)
+            .into_iter()
+            .zip(frets)
+            .map(|(note, fret)| fret.and(note))
+            .collect()
+    }
+    pub fn tune_courses(&self, frets: &[Option<i32>]) -> Vec<Note> {
+        /* Every sounding string, including the other strings of a course.
+         */
+        let mut result = vec![];
+        for (idx, opt_note) in self.tune_frets(frets).iter().enumerate() {
+            if let Some(note) = opt_note {
+                result.push(*note);
+                for offset in self.courses[idx].iter().filter(|o| **o != 0) {
+                    result.push(*note + Note { semitones: *offset });
+                }
+            }
+        }
+        result
+    }
 }
 #[test]
 fn test_tune() {
@@ -199,3 +424,72 @@ fn test_tune() {
     assert_eq!(result[1], Some(Note { semitones: 24 }));
     assert_eq!(result[2], Some(Note { semitones: 35 }));
 }
+#[test]
+fn test_from_string() {
+    let tuning = Tuning::from_string("C2 G2 C3 F3 C4 E4").unwrap();
+    let semitones = |t: &Tuning| {
+        t.get_basenotes()
+            .iter()
+            .map(|n| n.semitones)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(semitones(&tuning), vec![0, 7, 12, 17, 24, 28]);
+    assert_eq!(tuning.get_string_names()[3], "f");
+
+    let tuning = Tuning::from_string("d a d f# a d").unwrap();
+    assert_eq!(
+        semitones(&tuning),
+        semitones(&Tuning::from_name("opend").unwrap())
+    );
+
+    // Nashville high strung: octave strings on the low four
+    let tuning = Tuning::from_string("E3 A3 D4 G4 B3 E4").unwrap();
+    assert_eq!(semitones(&tuning), vec![16, 21, 26, 31, 23, 28]);
+
+    assert!(Tuning::from_string("E A D G H E").is_none());
+    assert!(Tuning::from_string("").is_none());
+}
+#[test]
+fn test_instruments() {
+    let bass = Tuning::from_name("bass").unwrap();
+    assert_eq!(bass.get_string_names(), &vec!["e", "a", "d", "g"]);
+    assert_eq!(
+        Tuning::from_name("eightstring")
+            .unwrap()
+            .get_basenotes()
+            .len(),
+        8
+    );
+
+    let ukulele = Tuning::from_name("ukulele").unwrap();
+    let result = ukulele.tune(&vec!["g", "c", "e", "a"], &vec![Note { semitones: 0 }; 4]);
+    assert_eq!(result[0], Some(Note { semitones: 31 }));
+    assert_eq!(result[1], Some(Note { semitones: 24 }));
+
+    let banjo = Tuning::from_name("banjo").unwrap();
+    assert_eq!(banjo.get_string_names()[0], "g");
+    assert_eq!(banjo.fret_pitch(0, 0), Some(Note { semitones: 31 }));
+    assert_eq!(banjo.fret_pitch(0, 3), None);
+    assert_eq!(banjo.fret_pitch(0, 7), Some(Note { semitones: 33 }));
+
+    let twelve = Tuning::from_name("twelvestring").unwrap();
+    let notes = twelve.tune_courses(&[Some(0), None, None, None, None, Some(0)]);
+    assert_eq!(
+        notes,
+        vec![
+            Note { semitones: 4 },
+            Note { semitones: 16 },
+            Note { semitones: 28 }
+        ]
+    );
+}
+#[test]
+fn test_capo() {
+    let tuning = Tuning::from_name("eadgbe").unwrap().with_capo(2);
+    assert_eq!(tuning.get_capo(), 2);
+    assert_eq!(tuning.get_basenotes()[0], Note { semitones: 6 });
+    /* the string names stay those of the open strings */
+    assert_eq!(tuning.get_string_names()[0], "e");
+    let open = tuning.with_capo(0);
+    assert_eq!(open.get_basenotes()[0], Note { semitones: 4 });
+}
@@ -0,0 +1,420 @@
+use super::chord::Chord;
+use super::note::Note;
+use super::tuning::Tuning;
+use std::cmp;
+use std::collections::HashMap;
+
+/* Voicings
+ * A voicing is one fret (or a muted string) per string of a tuning.
+ */
+pub struct VoicingOptions {
+    /* maximum distance between the lowest and highest fretted note */
+    pub max_span: i32,
+    pub max_fret: i32,
+    pub allow_open: bool,
+    /* require the root (or the slash bass) to be the lowest sounding note */
+    pub root_in_bass: bool,
+}
+impl Default for VoicingOptions {
+    fn default() -> VoicingOptions {
+        VoicingOptions {
+            max_span: 4,
+            max_fret: 12,
+            allow_open: true,
+            root_in_bass: true,
+        }
+    }
+}
+
+/* Chords with the voicing to play them with, if there is one */
+pub type VoicedChords = Vec<(Chord, Option<Voicing>)>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Voicing {
+    frets: Vec<Option<i32>>,
+    score: i32,
+}
+impl Voicing {
+    const MAX_FINGERS: usize = 4;
+    /* Highest fret of an open position shape */
+    const OPEN_POSITION: i32 = 3;
+    const MAX_CAPO: i32 = 7;
+
+    pub fn get_frets(&self) -> &Vec<Option<i32>> {
+        &self.frets
+    }
+    pub fn notes(&self, tuning: &Tuning) -> Vec<Note> {
+        tuning
+            .tune_frets(&self.frets)
+            .iter()
+            .filter_map(|n| *n)
+            .collect()
+    }
+    pub fn to_tab_notation(&self, tuning: &Tuning) -> String {
+        /* Muted strings are written as e.g. Ex, which the tab parser skips.
+         */
+        tuning
+            .get_string_names()
+            .iter()
+            .zip(self.frets.iter())
+            .map(|(string, fret)| match fret {
+                Some(f) => format!("{}{}", string.to_uppercase(), f),
+                None => format!("{}x", string.to_uppercase()),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn fingers(frets: &[Option<i32>]) -> usize {
+        /* Fretted notes on the lowest fret are assumed to be barred
+         * by a single finger, unless an open string lies in between.
+         */
+        let fretted = frets
+            .iter()
+            .filter_map(|f| *f)
+            .filter(|f| *f > 0)
+            .collect::<Vec<_>>();
+        let lowest = match fretted.iter().min() {
+            Some(lowest) => *lowest,
+            None => return 0,
+        };
+        let above_barre = fretted.iter().filter(|f| **f > lowest).count();
+        let first = frets.iter().position(|f| *f == Some(lowest)).unwrap();
+        let last = frets.iter().rposition(|f| *f == Some(lowest)).unwrap();
+        if frets[first..last].contains(&Some(0)) {
+            return fretted.len();
+        }
+        1 + above_barre
+    }
+    pub fn is_barre(&self) -> bool {
+        /* A finger laid across strings: no open string rings and the
+         * lowest fret is fretted on more than one string.
+         */
+        let fretted = self.frets.iter().filter_map(|f| *f).collect::<Vec<_>>();
+        if fretted.contains(&0) {
+            return false;
+        }
+        match fretted.iter().min() {
+            Some(lowest) => fretted.iter().filter(|f| *f == lowest).count() > 1,
+            None => false,
+        }
+    }
+    fn playability(frets: &[Option<i32>]) -> i32 {
+        /* Lower is easier: small stretches in low positions, few fingers,
+         * no muted strings between played ones.
+         */
+        let fretted = frets
+            .iter()
+            .filter_map(|f| *f)
+            .filter(|f| *f > 0)
+            .collect::<Vec<_>>();
+        let lowest = *fretted.iter().min().unwrap_or(&0);
+        let highest = *fretted.iter().max().unwrap_or(&0);
+        let first_played = frets.iter().position(|f| f.is_some()).unwrap_or(0);
+        let last_played = frets.iter().rposition(|f| f.is_some()).unwrap_or(0);
+        let inner_mutes = frets[first_played..last_played]
+            .iter()
+            .filter(|f| f.is_none())
+            .count() as i32;
+        let outer_mutes = frets.iter().filter(|f| f.is_none()).count() as i32 - inner_mutes;
+        let open_strings = frets.iter().filter(|f| **f == Some(0)).count() as i32;
+        2 * (highest - lowest)
+            + lowest
+            + 2 * Voicing::fingers(frets) as i32
+            + 6 * inner_mutes
+            + 2 * outer_mutes
+            - open_strings
+    }
+
+    fn enumerate(
+        candidates: &[Vec<(Option<i32>, u16)>],
+        required: u16,
+        min_strings: usize,
+        options: &VoicingOptions,
+        current: &mut Vec<(Option<i32>, u16)>,
+        result: &mut Vec<Vec<Option<i32>>>,
+    ) {
+        /* Candidates are the frets of each string with the pitch class
+         * they sound as a bit. A partial voicing is given up as soon as
+         * the strings left can no longer complete the chord.
+         */
+        let left = &candidates[current.len()..];
+        let covered = current.iter().fold(0, |set, (_, bit)| set | bit);
+        let reachable = left
+            .iter()
+            .flatten()
+            .fold(covered, |set, (_, bit)| set | bit);
+        let played = current.iter().filter(|(f, _)| f.is_some()).count();
+        let frets = current.iter().map(|(f, _)| *f).collect::<Vec<_>>();
+        if reachable & required != required
+            || played + left.len() < min_strings
+            || Voicing::fingers(&frets) > Voicing::MAX_FINGERS
+        {
+            return;
+        }
+        if left.is_empty() {
+            result.push(frets);
+            return;
+        }
+        let fretted = frets.iter().filter_map(|f| *f).filter(|f| *f > 0);
+        let (lowest, highest) = fretted.fold((i32::MAX, i32::MIN), |(lo, hi), f| {
+            (cmp::min(lo, f), cmp::max(hi, f))
+        });
+        for (fret, bit) in left[0].iter() {
+            if let Some(f) = fret {
+                if *f > 0 && (cmp::max(highest, *f) - cmp::min(lowest, *f) > options.max_span) {
+                    continue;
+                }
+            }
+            current.push((*fret, *bit));
+            Voicing::enumerate(candidates, required, min_strings, options, current, result);
+            current.pop();
+        }
+    }
+
+    pub fn find_voicings(chord: &Chord, tuning: &Tuning, options: &VoicingOptions) -> Vec<Voicing> {
+        /* Enumerates all playable fingerings of the chord, easiest first.
+         */
+        let mut tones = chord
+            .get_notes()
+            .iter()
+            .map(|n| n.pitch_class())
+            .collect::<Vec<_>>();
+        let root = tones[0];
+        let bass = chord.get_bass().map(|b| b.pitch_class()).unwrap_or(root);
+        if !tones.contains(&bass) {
+            tones.push(bass);
+        }
+        tones.sort();
+        tones.dedup();
+
+        let first_fret = if options.allow_open { 0 } else { 1 };
+        let candidates = tuning
+            .get_basenotes()
+            .iter()
+            .enumerate()
+            .map(|(idx, _)| {
+                let mut frets = vec![(None, 0)];
+                frets.extend((first_fret..=options.max_fret).filter_map(|f| {
+                    let pitch_class = tuning.fret_pitch(idx, f)?.pitch_class();
+                    match tones.contains(&pitch_class) {
+                        true => Some((Some(f), 1 << pitch_class.semitones)),
+                        false => None,
+                    }
+                }));
+                frets
+            })
+            .collect::<Vec<_>>();
+
+        /* the slash bass is never left out */
+        let omissible = chord
+            .omissible_tones()
+            .iter()
+            .map(|n| n.pitch_class())
+            .filter(|n| *n != bass)
+            .collect::<Vec<_>>();
+        let required = tones
+            .iter()
+            .filter(|t| !omissible.contains(t))
+            .fold(0u16, |set, t| set | 1 << t.semitones);
+        let min_strings = cmp::max(3, required.count_ones() as usize);
+        let mut fingerings = vec![];
+        Voicing::enumerate(
+            &candidates,
+            required,
+            min_strings,
+            options,
+            &mut vec![],
+            &mut fingerings,
+        );
+
+        let mut verified = HashMap::new();
+        let mut voicings = fingerings
+            .into_iter()
+            .map(|frets| Voicing {
+                score: Voicing::playability(&frets),
+                frets,
+            })
+            .filter(|voicing| {
+                let lowest = voicing.notes(tuning).into_iter().min().unwrap();
+                !options.root_in_bass || lowest.pitch_class() == bass
+            })
+            .filter(|voicing| {
+                /* voicings with the same pitch classes are checked once */
+                let notes = voicing.notes(tuning);
+                *verified
+                    .entry(Chord::pitch_class_set(&notes))
+                    .or_insert_with(|| Voicing::verify(chord, &notes))
+            })
+            .collect::<Vec<_>>();
+        voicings.sort_by_key(|v1| v1.score);
+        voicings
+    }
+
+    pub fn open_shape(chord: &Chord, tuning: &Tuning) -> Option<Voicing> {
+        /* The easiest voicing in the first frets that rings at least
+         * one open string, e.g. the C, A, G, E and D shapes.
+         */
+        let options = VoicingOptions {
+            max_fret: Voicing::OPEN_POSITION,
+            ..Default::default()
+        };
+        Voicing::find_voicings(chord, tuning, &options)
+            .into_iter()
+            .find(|v| v.frets.contains(&Some(0)))
+    }
+    pub fn capo_positions(chords: &[Chord], tuning: &Tuning) -> Vec<(i32, VoicedChords)> {
+        /* For every capo position the shapes to play the chords with and
+         * their open voicing, if there is one. Positions with the most
+         * open shapes come first, then the easier and lower ones.
+         */
+        let open_tuning = tuning.clone().with_capo(0);
+        let mut positions = (0..=Voicing::MAX_CAPO)
+            .map(|capo| {
+                let shapes = chords
+                    .iter()
+                    .map(|chord| {
+                        let shape = chord.transposed(-capo);
+                        let voicing = Voicing::open_shape(&shape, &open_tuning);
+                        (shape, voicing)
+                    })
+                    .collect::<Vec<_>>();
+                (capo, shapes)
+            })
+            .collect::<Vec<_>>();
+        positions.sort_by_key(|(capo, shapes)| {
+            let open = shapes.iter().filter_map(|(_, v)| v.as_ref());
+            let score = open.clone().map(|v| v.score).sum::<i32>();
+            (-(open.count() as i32), score, *capo)
+        });
+        positions
+    }
+
+    pub fn transpositions(chords: &[Chord], tuning: &Tuning) -> Vec<(i32, VoicedChords)> {
+        /* For every transposition between a fifth down and a tritone
+         * up the chords and their easiest voicing, if there is one.
+         * Transpositions with the fewest barre chords come first, then
+         * the easier and smaller ones.
+         */
+        let options = VoicingOptions::default();
+        let mut transpositions = (-5..=6)
+            .map(|semitones| {
+                let chords = chords
+                    .iter()
+                    .map(|chord| {
+                        let chord = chord.transposed(semitones);
+                        let voicing = Voicing::find_voicings(&chord, tuning, &options)
+                            .into_iter()
+                            .next();
+                        (chord, voicing)
+                    })
+                    .collect::<Vec<_>>();
+                (semitones, chords)
+            })
+            .collect::<Vec<_>>();
+        transpositions.sort_by_key(|(semitones, chords)| {
+            let barres = chords
+                .iter()
+                .filter(|(_, v)| v.as_ref().is_none_or(|v| v.is_barre()))
+                .count();
+            let score = chords
+                .iter()
+                .filter_map(|(_, v)| v.as_ref())
+                .map(|v| v.score)
+                .sum::<i32>();
+            (barres, score, semitones.abs())
+        });
+        transpositions
+    }
+
+    fn verify(chord: &Chord, notes: &[Note]) -> bool {
+        /* The voicing has to be recognised as a chord on the same root.
+         * Chords outside of find_chord's vocabulary can't be verified
+         * and are accepted on the basis of their tones.
+         */
+        let root = chord.get_notes()[0].pitch_class();
+        let reference = Chord::find_chord(chord.get_notes(), false);
+        if reference.iter().all(|c| c.is_none()) {
+            return true;
+        }
+        Chord::find_chord(notes, false)
+            .iter()
+            .filter_map(|c| c.as_ref())
+            .any(|c| c.get_notes()[0].pitch_class() == root)
+    }
+}
+#[test]
+fn test_find_voicings() {
+    let tuning = Tuning::from_name("eadgbe").unwrap();
+    let options = VoicingOptions::default();
+
+    let e_major = Chord::from_symbol("E").unwrap();
+    let voicings = Voicing::find_voicings(&e_major, &tuning, &options);
+    let tabs = voicings
+        .iter()
+        .map(|v| v.to_tab_notation(&tuning))
+        .collect::<Vec<_>>();
+    assert_eq!(tabs[0], "E0 A2 D2 G1 B0 E0");
+
+    let c_major = Chord::from_symbol("C").unwrap();
+    let voicings = Voicing::find_voicings(&c_major, &tuning, &options);
+    let tabs = voicings
+        .iter()
+        .take(5)
+        .map(|v| v.to_tab_notation(&tuning))
+        .collect::<Vec<_>>();
+    assert!(tabs.contains(&String::from("Ex A3 D2 G0 B1 E0")));
+    for voicing in voicings.iter() {
+        let lowest = voicing.notes(&tuning).into_iter().min().unwrap();
+        assert_eq!(lowest.pitch_class(), Note { semitones: 0 });
+    }
+
+    /* the E shape stretches over two frets, one fret apart */
+    let narrow = VoicingOptions {
+        max_span: 1,
+        ..Default::default()
+    };
+    let voicings = Voicing::find_voicings(&e_major, &tuning, &narrow);
+    assert_eq!(voicings[0].to_tab_notation(&tuning), "E0 A2 D2 G1 B0 E0");
+
+    /* a thirteenth fits on six strings without its fifth and eleventh */
+    let c13 = Chord::from_symbol("C13").unwrap();
+    let voicings = Voicing::find_voicings(&c13, &tuning, &options);
+    assert!(!voicings.is_empty());
+    let pitch_classes = Chord::pitch_class_set(&voicings[0].notes(&tuning));
+    assert_eq!(pitch_classes & 1 << 5, 0);
+}
+#[test]
+fn test_capo_positions() {
+    let tuning = Tuning::from_name("eadgbe").unwrap();
+    let song = ["Bb", "F", "Gm", "Eb"]
+        .iter()
+        .map(|s| Chord::from_symbol(s).unwrap())
+        .collect::<Vec<_>>();
+    let positions = Voicing::capo_positions(&song, &tuning);
+    let (capo, shapes) = &positions[0];
+    assert_eq!(*capo, 3);
+    let names = shapes
+        .iter()
+        .map(|(c, _)| c.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["G", "D", "Em", "C"]);
+    assert!(shapes.iter().all(|(_, v)| v.is_some()));
+}
+#[test]
+fn test_transpositions() {
+    let tuning = Tuning::from_name("eadgbe").unwrap();
+    let f_major = Chord::from_symbol("F").unwrap();
+    let options = VoicingOptions {
+        allow_open: false,
+        ..Default::default()
+    };
+    assert!(Voicing::find_voicings(&f_major, &tuning, &options)[0].is_barre());
+    let song = ["Bb", "F", "Gm", "Eb"]
+        .iter()
+        .map(|s| Chord::from_symbol(s).unwrap())
+        .collect::<Vec<_>>();
+    let (semitones, chords) = &Voicing::transpositions(&song, &tuning)[0];
+    assert_eq!(*semitones, -3);
+    assert!(chords.iter().all(|(_, v)| !v.as_ref().unwrap().is_barre()));
+}
@@ -18,8 +18,100 @@ fn main() {
                                      .short("s")
                                      .long("scale")
                                      .help("Print out any scale for a given root. 
-                                            Example: guitar_notes -s E {major, minor, minor_blues, major_blues, minor_pentatonic, major_pentatonic, dorian, lydian, mixolydin}")
+                                            Example: guitar_notes -s E {major, minor, minor_blues, dorian, harmonic_minor, altered, whole_tone, hirajoshi, ...}")
                                      .number_of_values(2) )
+                                .arg(Arg::with_name("scale_formula")
+                                     .long("scale-formula")
+                                     .help("Print out a scale given by its degrees or steps for a given root.
+                                            Example: guitar_notes --scale-formula A \"1 2 b3 4 5 b6 7\" or guitar_notes --scale-formula A \"W H W W H A H\"")
+                                     .number_of_values(2) )
+                                .arg(Arg::with_name("identify_scale")
+                                    .short("i")
+                                    .long("identify-scale")
+                                    .multiple(true)
+                                    .help("Lists the scales that contain the given notes, in tab notation or as note names. Example: guitar_notes -i E5 E8 A5 A7 D5 D7 or guitar_notes -i a c d e g")
+                                    .takes_value(true))
+                                .arg(Arg::with_name("key")
+                                    .short("k")
+                                    .long("key")
+                                    .multiple(true)
+                                    .help("Finds the most likely keys of a chord progression and flags borrowed chords and secondary dominants. Chords are symbols or tab notation. Example: guitar_notes -k C Am D7 G or guitar_notes -k x,a3,d2,g0,b1,e0 G")
+                                    .takes_value(true))
+                                .arg(Arg::with_name("roman")
+                                    .long("roman")
+                                    .min_values(2)
+                                    .help("Roman numeral analysis of a chord progression in a key (C, Am, \"D dorian\"). Example: guitar_notes --roman C Dm7 G7 Cmaj7")
+                                    .takes_value(true))
+                                .arg(Arg::with_name("from_roman")
+                                    .long("from-roman")
+                                    .min_values(2)
+                                    .help("Chord symbols and notes of Roman numerals in a key. Example: guitar_notes --from-roman Bb ii V7 Imaj7")
+                                    .takes_value(true))
+                                .arg(Arg::with_name("tab_file")
+                                    .long("tab-file")
+                                    .help("Reads an ASCII tab file (e|---0---|) and names the chord of every note event, followed by a chord chart. Example: guitar_notes --tab-file song.txt")
+                                    .takes_value(true))
+                                .arg(Arg::with_name("midi_file")
+                                    .long("midi-file")
+                                    .help("Reads a MIDI file, names its chords, finds the key and writes the highest voice as tab in the tuning (within --frets). Example: guitar_notes --midi-file backing.mid")
+                                    .takes_value(true))
+                                .arg(Arg::with_name("chordpro")
+                                    .long("chordpro")
+                                    .help("Prints a ChordPro song file ([Am]lyrics, {title: ...}) as a lead sheet with chord boxes. With --capo the chords are the shapes to play. Example: guitar_notes --chordpro song.cho --transpose Bb")
+                                    .takes_value(true))
+                                .arg(Arg::with_name("transpose")
+                                    .long("transpose")
+                                    .help("Transposes --chordpro and --progression by semitones (2, -3) or to a key (Bb, Em).")
+                                    .allow_hyphen_values(true)
+                                    .takes_value(true))
+                                .arg(Arg::with_name("progression")
+                                    .long("progression")
+                                    .multiple(true)
+                                    .help("Transposes a chord progression by --transpose, with the chords spelled in the new key, or lists the keys with the fewest barre chords with --easiest-key. Example: guitar_notes --progression Am F C G --transpose Bb")
+                                    .takes_value(true))
+                                .arg(Arg::with_name("easiest_key")
+                                    .long("easiest-key")
+                                    .help("Lists the transpositions of --progression with the fewest barre chords in the tuning.")
+                                    .takes_value(false))
+                                .arg(Arg::with_name("write_chordpro")
+                                    .long("write-chordpro")
+                                    .help("Prints --chordpro as ChordPro instead of a lead sheet, e.g. to save the transposed song.")
+                                    .takes_value(false))
+                                .arg(Arg::with_name("chord")
+                                    .short("c")
+                                    .long("chord")
+                                    .help("Prints out the notes and intervals of a chord symbol and shows them on the fretboard. Example: guitar_notes -c F#m7b5")
+                                    .takes_value(true))
+                                .arg(Arg::with_name("voicings")
+                                    .short("v")
+                                    .long("voicings")
+                                    .multiple(true)
+                                    .help("Lists playable fingerings of a chord symbol or a set of notes (root first). Example: guitar_notes -v Am7 or guitar_notes -v a c e g")
+                                    .takes_value(true))
+                                .arg(Arg::with_name("span")
+                                    .long("span")
+                                    .help("Maximum hand span in frets for --voicings.")
+                                    .default_value("4")
+                                    .takes_value(true))
+                                .arg(Arg::with_name("no_open")
+                                    .long("no-open")
+                                    .help("Do not use open strings in --voicings.")
+                                    .takes_value(false))
+                                .arg(Arg::with_name("any_bass")
+                                    .long("any-bass")
+                                    .help("Allow --voicings without the root in the bass.")
+                                    .takes_value(false))
+                                .arg(Arg::with_name("pitch")
+                                    .short("p")
+                                    .long("pitch")
+                                    .multiple(true)
+                                    .help("Prints MIDI number and frequency of pitches in scientific notation, MIDI numbers or frequencies (415Hz). Example: guitar_notes -p E2 A4 64")
+                                    .takes_value(true))
+                                .arg(Arg::with_name("a4")
+                                    .long("a4")
+                                    .help("Reference frequency of A4 in Hz for --pitch.")
+                                    .global(true)
+                                    .default_value("440"))
                                 .arg(Arg::with_name("all_notes")
                                     .short("a")
                                     .multiple(true)
@@ -29,9 +121,39 @@ fn main() {
                                 .arg(Arg::with_name("tuning")
                                     .short("t")
                                     .long("tuning")
-                                    .help("Set a guitar tuning different from EADGBE: {DROPD,...}")
+                                    .help("Set a guitar tuning different from EADGBE: {DROPD,...}, a tuning from the [tunings] section of ~/.guitarnotes or the string pitches from low to high, e.g. \"C2 G2 C3 F3 C4 E4\"")
                                     .global(true)
                                     .default_value("eadgbe"))
+                                .arg(Arg::with_name("capo")
+                                    .long("capo")
+                                    .help("Put a capo on the given fret: tab input and fretboards count frets from the capo, notes and chords are in concert pitch.")
+                                    .global(true)
+                                    .default_value("0"))
+                                .arg(Arg::with_name("absolute")
+                                    .long("absolute")
+                                    .help("Number the frets from the nut rather than from the capo.")
+                                    .global(true)
+                                    .takes_value(false))
+                                .arg(Arg::with_name("neck")
+                                    .long("neck")
+                                    .help("Number of frets of the neck, e.g. 19 for a classical guitar.")
+                                    .global(true)
+                                    .default_value("24"))
+                                .arg(Arg::with_name("frets")
+                                    .long("frets")
+                                    .help("Only print the given frets of the fretboard (counted from the capo). Example: guitar_notes -s a minor --frets 5-9")
+                                    .global(true)
+                                    .takes_value(true))
+                                .arg(Arg::with_name("harmony")
+                                    .long("harmony")
+                                    .help("How the chords of --scale and --scale-formula are stacked: {triads, sevenths, ninths, sus2, sus4, quartal}.")
+                                    .global(true)
+                                    .default_value("triads"))
+                                .arg(Arg::with_name("find_capo")
+                                    .long("find-capo")
+                                    .multiple(true)
+                                    .help("Suggests capo positions that let you play the chords of a song in open shapes. Example: guitar_notes --find-capo Bb F Gm Eb")
+                                    .takes_value(true))
                                 .arg(Arg::with_name("relative")
                                     .short("r")
                                     .long("relative")
@@ -39,6 +161,61 @@ fn main() {
                                     .global(true)
                                     .takes_value(false)
                                     )
+                                .arg(Arg::with_name("box")
+                                    .short("b")
+                                    .long("box")
+                                    .help("Print chords from --note and --voicings as vertical chord boxes.")
+                                    .global(true)
+                                    .takes_value(false)
+                                    )
+                                .arg(Arg::with_name("rootless")
+                                    .long("rootless")
+                                    .help("With --note, also name shell and rootless voicings over roots that aren't played.")
+                                    .takes_value(false)
+                                    )
+                                .arg(Arg::with_name("tab")
+                                    .long("tab")
+                                    .help("Also write --scale, --scale-formula and --chord as ascending and descending tab in the first position of --frets, and --voicings as tab.")
+                                    .global(true)
+                                    .takes_value(false)
+                                    )
+                                .arg(Arg::with_name("midi")
+                                    .long("midi")
+                                    .help("Writes --note (strummed), --scale (the --tab exercise), --progression and --tab-file to a Standard MIDI File. Example: guitar_notes --progression Am F C G --midi song.mid")
+                                    .global(true)
+                                    .takes_value(true))
+                                .arg(Arg::with_name("tempo")
+                                    .long("tempo")
+                                    .help("Tempo of --midi in quarter notes per minute.")
+                                    .global(true)
+                                    .default_value("100"))
+                                .arg(Arg::with_name("velocity")
+                                    .long("velocity")
+                                    .help("Velocity of the notes of --midi (1-127).")
+                                    .global(true)
+                                    .default_value("96"))
+                                .arg(Arg::with_name("strum")
+                                    .long("strum")
+                                    .help("Delay between the strings of a strummed chord in --midi, in milliseconds.")
+                                    .global(true)
+                                    .default_value("15"))
+                                .arg(Arg::with_name("program")
+                                    .long("program")
+                                    .help("General MIDI program of --midi (1-128): 25 nylon, 26 steel, 27 jazz, 28 clean, 30 overdriven, 31 distortion guitar, 34 bass.")
+                                    .global(true)
+                                    .default_value("26"))
+                                .arg(Arg::with_name("midi_format")
+                                    .long("midi-format")
+                                    .help("Standard MIDI File type of --midi: 0 (one track) or 1 (tempo and note tracks).")
+                                    .global(true)
+                                    .default_value("1"))
+                                .arg(Arg::with_name("unicode")
+                                    .short("u")
+                                    .long("unicode")
+                                    .help("Draw chord boxes with unicode box drawing characters.")
+                                    .global(true)
+                                    .takes_value(false)
+                                    )
                                 .get_matches();
     // See if the user set a tuning
     let opt_tuning =
@@ -59,12 +236,110 @@ fn main() {
                  opene,
                  openg,
                  opena,
-                 openc"
+                 openc,
+                 bass, bass5, bass6,
+                 sevenstring, eightstring, twelvestring,
+                 ukulele, baritoneukulele, mandolin, banjo,
+                 a tuning from the [tunings] section of ~/.guitarnotes
+                 or the string pitches from low to high, e.g. \"C2 G2 C3 F3 C4 E4\" or \"D A D F# A D\""
             );
             println!("I will continue and assume standard tuning: eadgbe");
             guitar_note::parse_tuning("eadgbe").unwrap()
         }
     };
+    if let Some(chord_str) = matches.values_of("find_capo") {
+        let chord_str = chord_str.map(|s| s.to_owned()).collect::<Vec<_>>();
+        match guitar_note::capo_for_open_shapes(&chord_str, &tuning) {
+            Some(lines) => {
+                for line in lines.iter().take(3) {
+                    println!("{}", line);
+                }
+            }
+            None => println!("Sorry, I could not parse the chord symbols (e.g. Am7, Bb, C/G)."),
+        }
+    }
+    // frets are counted from the capo
+    let capo = matches
+        .value_of("capo")
+        .unwrap()
+        .parse::<i32>()
+        .ok()
+        .filter(|c| *c >= 0)
+        .unwrap_or(0);
+    let tuning = guitar_note::with_capo(tuning, capo);
+    let fret_offset = if matches.is_present("absolute") { capo } else { 0 };
+    // the part of the neck to print
+    let neck = matches
+        .value_of("neck")
+        .unwrap()
+        .parse::<i32>()
+        .unwrap_or(guitar_note::FretWindow::DEFAULT_NECK);
+    let window = match matches.value_of("frets") {
+        Some(spec) => match guitar_note::FretWindow::from_string(spec, neck - capo) {
+            Some(window) => window,
+            None => {
+                println!(
+                    "WARNING: could not parse the fret window {}. It should look like 5-9 and lie on the neck.",
+                    spec
+                );
+                guitar_note::FretWindow::new(neck - capo)
+            }
+        },
+        None => guitar_note::FretWindow::new(neck - capo),
+    };
+    // the chords printed with a scale
+    let harmony = match matches.value_of("harmony").unwrap().to_lowercase().parse() {
+        Ok(harmony) => harmony,
+        Err(_) => {
+            println!("WARNING: could not parse the harmony. It should be one of triads, sevenths, ninths, sus2, sus4 or quartal.");
+            guitar_note::Harmony::triads
+        }
+    };
+    // MIDI export
+    let midi_options = guitar_note::MidiOptions {
+        tempo: matches
+            .value_of("tempo")
+            .unwrap()
+            .parse::<u32>()
+            .ok()
+            .filter(|t| *t > 0)
+            .unwrap_or(100),
+        velocity: matches
+            .value_of("velocity")
+            .unwrap()
+            .parse::<u8>()
+            .ok()
+            .filter(|v| *v > 0 && *v < 128)
+            .unwrap_or(96),
+        strum_ms: matches.value_of("strum").unwrap().parse::<u32>().unwrap_or(15),
+        program: matches
+            .value_of("program")
+            .unwrap()
+            .parse::<u8>()
+            .ok()
+            .filter(|p| *p > 0 && *p <= 128)
+            .map_or(25, |p| p - 1),
+        format: match matches.value_of("midi_format").unwrap() {
+            "0" => 0,
+            _ => 1,
+        },
+    };
+    let write_midi = |bytes: Option<Vec<u8>>| {
+        let path = matches.value_of("midi").unwrap();
+        match bytes.map(|b| std::fs::write(path, b)) {
+            Some(Ok(_)) => println!("MIDI written to {}.", path),
+            Some(Err(_)) => println!("Sorry, I could not write {}.", path),
+            None => println!("Sorry, there was nothing to write to {}.", path),
+        }
+    };
+    // fret numbers and inlay markers lined up with a fretboard layout
+    let fret_rows = |fretboard: &str| {
+        let width = guitar_note::label_width(fretboard);
+        (
+            guitar_note::print_fret_numbers(&window, fret_offset, width),
+            guitar_note::print_fret_markers(&window, capo, width),
+        )
+    };
     // parse note arguments
     match matches.values_of("note") {
         Some(note_str) => {
@@ -74,29 +349,45 @@ fn main() {
             let note_results = guitar_note::from_tab_notation(&notes, &tuning);
             if let Some(results) = note_results {
                 println!("{}", results);
-                let fret_numbers = guitar_note::print_fret_numbers();
-                let fret_markers = guitar_note::print_fret_markers();
                 let (chord_strings, fretboard) =
-                    guitar_note::chord_from_tab_notation(&notes, &tuning, relative);
+                    guitar_note::chord_from_tab_notation(
+                        &notes,
+                        &tuning,
+                        relative,
+                        &window,
+                        matches.is_present("rootless"),
+                    );
 
-                if chord_strings.is_empty() || chord_strings.iter().all(|s| s.is_none()) {
+                if chord_strings.is_empty() {
                     println!("This is not a chord that I know.");
+                    let closest = guitar_note::closest_chords(&notes, &tuning, 3);
+                    if !closest.is_empty() {
+                        println!("Closest: {}", closest.join(", "));
+                    }
                 } else {
-                    for (idx, opt_chord_string) in chord_strings.iter().enumerate() {
-                        if let Some(chord_string) = opt_chord_string {
-                            if idx == 0 {
-                                println!("Chord: {}", chord_string);
-                            } else {
-                                println!("{}. inversion: {}", idx, chord_string);
-                            }
+                    println!("Chord: {}", chord_strings[0]);
+                    if chord_strings.len() > 1 {
+                        println!("Also: {}", chord_strings[1..].join(", "));
+                    }
+                    if matches.is_present("box") {
+                        let unicode = matches.is_present("unicode");
+                        if let Some(chord_box) =
+                            guitar_note::chord_box_from_tab_notation(&notes, &tuning, unicode)
+                        {
+                            println!("{}", chord_box);
                         }
+                    } else {
+                        // print the notes on the fretboard
+                        let (fret_numbers, fret_markers) = fret_rows(&fretboard);
+                        println!("{}", fret_numbers);
+                        println!();
+                        println!("{}", fretboard);
+                        println!();
+                        println!("{}", fret_markers);
                     }
-                    // print the notes on the fretboard
-                    println!("{}", fret_numbers);
-                    print!("\n");
-                    println!("{}", fretboard);
-                    print!("\n");
-                    println!("{}", fret_markers);
+                }
+                if matches.is_present("midi") {
+                    write_midi(guitar_note::tab_midi(&notes, &tuning, &midi_options));
                 }
             } else {
                 println!(
@@ -106,13 +397,240 @@ fn main() {
         }
         None => {}
     }
+    if let Some(note_str) = matches.values_of("identify_scale") {
+        let notes = note_str.map(|s| s.to_lowercase()).collect::<Vec<_>>();
+        match guitar_note::identify_scale(&notes, &tuning) {
+            Some(lines) => {
+                for (idx, line) in lines.iter().take(10).enumerate() {
+                    println!("{}.\t{}", idx + 1, line);
+                }
+            }
+            None => println!(
+                "Sorry, I could not parse the notes. Use tab notation (E0, A13) or note names (a, c#, bb)."
+            ),
+        }
+    }
+    if let Some(chord_str) = matches.values_of("key") {
+        let chord_str = chord_str.map(|s| s.to_owned()).collect::<Vec<_>>();
+        match guitar_note::detect_key(&chord_str, &tuning) {
+            Some(lines) => {
+                for (idx, line) in lines.iter().take(5).enumerate() {
+                    println!("{}.\t{}", idx + 1, line);
+                }
+            }
+            None => println!(
+                "Sorry, I could not parse the chords. Use chord symbols (Am7, C/G) or tab notation (a0,d2,g2,b1,e0)."
+            ),
+        }
+    }
+    if let Some(path) = matches.value_of("tab_file") {
+        match std::fs::read_to_string(path) {
+            Ok(text) => match guitar_note::tab_chord_chart(&text, &tuning) {
+                Some((lines, chart)) => {
+                    println!("Bar\tFrets\tChord");
+                    for line in lines {
+                        println!("{}", line);
+                    }
+                    println!("Chords: {}", chart);
+                    if matches.is_present("midi") {
+                        write_midi(guitar_note::tab_file_midi(&text, &tuning, &midi_options));
+                    }
+                }
+                None => println!(
+                    "Sorry, I found no tab in {}. Each string needs a line like e|---0---|.",
+                    path
+                ),
+            },
+            Err(_) => println!("Sorry, I could not read {}.", path),
+        }
+    }
+    if let Some(chord_str) = matches.values_of("progression") {
+        let chord_str = chord_str.map(|s| s.to_owned()).collect::<Vec<_>>();
+        if matches.is_present("midi") {
+            let midi = guitar_note::progression_midi(&chord_str, &tuning, &midi_options);
+            if let Some((_, skipped)) = &midi {
+                for symbol in skipped {
+                    println!("Sorry, I could not play {} (not a chord I know or no voicing in the tuning), its bar is left empty.", symbol);
+                }
+            }
+            write_midi(midi.map(|(bytes, _)| bytes));
+        }
+        if matches.is_present("easiest_key") {
+            match guitar_note::easiest_keys(&chord_str, &tuning) {
+                Some(lines) => {
+                    for line in lines.iter().take(3) {
+                        println!("{}", line);
+                    }
+                }
+                None => println!("Sorry, I could not parse the chords (e.g. Am7, Bb, C/G)."),
+            }
+        }
+        match matches.value_of("transpose") {
+            Some(transpose) => {
+                match guitar_note::transpose_progression(&chord_str, transpose, &tuning) {
+                    Some((key, names)) => {
+                        println!("Key: {}", key);
+                        println!("{}", names);
+                    }
+                    None => println!("Sorry, I could not parse the chords (e.g. Am7, Bb, C/G) or the transposition (2, -3, Bb, Em)."),
+                }
+            }
+            None if !matches.is_present("easiest_key") && !matches.is_present("midi") => {
+                println!("Please tell me how to transpose with --transpose (2, -3, Bb, Em) or ask for --easiest-key.")
+            }
+            None => {}
+        }
+    }
+    if let Some(path) = matches.value_of("midi_file") {
+        match std::fs::read(path) {
+            Ok(bytes) => match guitar_note::midi_chords(&bytes) {
+                Some((lines, chart, key)) => {
+                    if !lines.is_empty() {
+                        println!("Beat\tNotes\tChord");
+                        for line in lines {
+                            println!("{}", line);
+                        }
+                        println!("Chords: {}", chart);
+                    }
+                    if let Some(key) = key {
+                        println!("Key: {}", key);
+                    }
+                    if let Some(tab) = guitar_note::midi_melody_tab(&bytes, &tuning, &window) {
+                        println!("Melody:");
+                        println!("{}", tab);
+                    }
+                }
+                None => println!("Sorry, {} is not a MIDI file that I can read.", path),
+            },
+            Err(_) => println!("Sorry, I could not read {}.", path),
+        }
+    }
+    if let Some(path) = matches.value_of("chordpro") {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                let transpose = matches.value_of("transpose");
+                let song = match matches.is_present("write_chordpro") {
+                    true => guitar_note::chordpro_transposed(&text, transpose, &tuning),
+                    false => guitar_note::chordpro_lead_sheet(
+                        &text,
+                        transpose,
+                        &tuning,
+                        matches.is_present("unicode"),
+                    ),
+                };
+                match song {
+                    Some(song) => println!("{}", song),
+                    None => println!(
+                        "Sorry, I found no song in {} or could not transpose it. Chords are written like [Am], directives like {{title: ...}}.",
+                        path
+                    ),
+                }
+            }
+            Err(_) => println!("Sorry, I could not read {}.", path),
+        }
+    }
+    if let Some(values) = matches.values_of("roman") {
+        let values = values.map(|s| s.to_owned()).collect::<Vec<_>>();
+        match guitar_note::roman_numerals(&values[0], &values[1..], &tuning) {
+            Some((key, names, numerals)) => {
+                println!("Key: {}", key);
+                println!("{}", names);
+                println!("{}", numerals);
+            }
+            None => println!(
+                "Sorry, I could not parse the key (e.g. C, Am, \"D dorian\") or the chords (e.g. Am7, a0,d2,g2,b1,e0)."
+            ),
+        }
+    }
+    if let Some(values) = matches.values_of("from_roman") {
+        let values = values.map(|s| s.to_owned()).collect::<Vec<_>>();
+        match guitar_note::chords_from_roman(&values[0], &values[1..]) {
+            Some(lines) => {
+                for line in lines {
+                    println!("{}", line);
+                }
+            }
+            None => println!(
+                "Sorry, I could not parse the key (e.g. C, Am, \"D dorian\") or the numerals (e.g. ii7, V6/5, bVII, V7/V)."
+            ),
+        }
+    }
+    if let Some(symbol) = matches.value_of("chord") {
+        let relative = matches.is_present("relative");
+        if let Some((notes, intervals, fretboard)) =
+            guitar_note::chord_from_symbol(symbol, &tuning, relative, &window)
+        {
+            println!("Chord: {}", symbol);
+            println!("Notes: {}", notes);
+            println!("Intervals: {}", intervals);
+            let (fret_numbers, fret_markers) = fret_rows(&fretboard);
+            println!("{}", fret_numbers);
+            println!();
+            println!("{}", fretboard);
+            println!();
+            println!("{}", fret_markers);
+            if matches.is_present("tab") {
+                if let Some(tab) = guitar_note::chord_tab(symbol, &tuning, &window) {
+                    println!();
+                    println!("{}", tab);
+                }
+            }
+        } else {
+            println!("Sorry, I could not parse the chord symbol (e.g. Am7, F#m7b5, C/G).");
+        }
+    }
+    if let Some(chord_str) = matches.values_of("voicings") {
+        let chord_str = chord_str.map(|s| s.to_owned()).collect::<Vec<_>>();
+        let options = guitar_note::VoicingOptions {
+            max_span: matches.value_of("span").unwrap().parse().unwrap_or(4),
+            allow_open: !matches.is_present("no_open"),
+            root_in_bass: !matches.is_present("any_bass"),
+            ..Default::default()
+        };
+        match guitar_note::chord_voicings(&chord_str, &tuning, &options) {
+            Some((name, voicings)) if !voicings.is_empty() => {
+                println!("Voicings for {}:", name);
+                for (idx, voicing) in voicings.iter().take(10).enumerate() {
+                    println!("{}.\t{}", idx + 1, voicing);
+                    if matches.is_present("box") {
+                        let tab = voicing
+                            .split_whitespace()
+                            .map(|s| s.to_lowercase())
+                            .collect::<Vec<_>>();
+                        let unicode = matches.is_present("unicode");
+                        if let Some(chord_box) =
+                            guitar_note::chord_box_from_tab_notation(&tab, &tuning, unicode)
+                        {
+                            println!("{}\n", chord_box);
+                        }
+                    }
+                }
+                if matches.is_present("tab") {
+                    if let Some(tab) = guitar_note::voicings_tab(&chord_str, &tuning, &options, 10) {
+                        println!();
+                        println!("{}", tab);
+                    }
+                }
+            }
+            Some((name, _)) => println!("I could not find a playable voicing of {}.", name),
+            None => println!("Sorry, I could not parse the chord (e.g. Am7 or a c e g)."),
+        }
+    }
+    if let Some(pitch_str) = matches.values_of("pitch") {
+        let pitch_str = pitch_str.map(|s| s.to_owned()).collect::<Vec<_>>();
+        let reference_a4 = matches
+            .value_of("a4")
+            .unwrap()
+            .parse::<f64>()
+            .unwrap_or(440.0);
+        println!("{}", guitar_note::pitch_info(&pitch_str, reference_a4).join("\n"));
+    }
     match matches.values_of("all_notes") {
         Some(note_names) => {
-            let fret_numbers = guitar_note::print_fret_numbers();
-            let fret_markers = guitar_note::print_fret_markers();
             let notes = note_names.map(|s| s.to_lowercase()).collect::<Vec<_>>();
-            let opt_fretboard = guitar_note::all_notes_on_fretboard(&notes, &tuning);
+            let opt_fretboard = guitar_note::all_notes_on_fretboard(&notes, &tuning, &window);
             if let Some(fretboard) = opt_fretboard {
+                let (fret_numbers, fret_markers) = fret_rows(&fretboard);
                 println!("{}", fret_numbers);
                 print!("\n");
                 println!("{}", fretboard);
@@ -127,49 +645,89 @@ fn main() {
     match matches.values_of("scale") {
         Some(mut vals) => {
             let relative = matches.is_present("relative");
-            let fret_numbers = guitar_note::print_fret_numbers();
-            let fret_markers = guitar_note::print_fret_markers();
             let scale_name = &vals.nth(0).unwrap().to_lowercase()[..];
             let root_name = &vals.nth(0).unwrap().to_lowercase()[..];
             if let Some(result) =
-                guitar_note::scale_on_fretboard(scale_name, root_name, &tuning, relative)
+                guitar_note::scale_on_fretboard(scale_name, root_name, &tuning, relative, &window, &harmony)
             {
                 println!("{}", result.2);
                 println!("{}", result.1);
                 print!("\n");
+                let (fret_numbers, fret_markers) = fret_rows(&result.0);
                 println!("{}", fret_numbers);
                 print!("\n");
                 println!("{}", result.0);
                 print!("\n");
                 println!("{}", fret_markers);
+                if matches.is_present("tab") {
+                    if let Some(tab) = guitar_note::scale_tab(scale_name, root_name, &tuning, &window) {
+                        println!();
+                        println!("{}", tab);
+                    }
+                }
+                if matches.is_present("midi") {
+                    write_midi(guitar_note::scale_midi(
+                        scale_name, root_name, &tuning, &window, &midi_options,
+                    ));
+                }
             } else if let Some(result) =
-                guitar_note::scale_on_fretboard(root_name, scale_name, &tuning, relative)
+                guitar_note::scale_on_fretboard(root_name, scale_name, &tuning, relative, &window, &harmony)
             {
                 println!("{}", result.2);
                 println!("{}", result.1);
                 print!("\n");
+                let (fret_numbers, fret_markers) = fret_rows(&result.0);
                 println!("{}", fret_numbers);
                 print!("\n");
                 println!("{}", result.0);
                 print!("\n");
                 println!("{}", fret_markers);
+                if matches.is_present("tab") {
+                    if let Some(tab) = guitar_note::scale_tab(root_name, scale_name, &tuning, &window) {
+                        println!();
+                        println!("{}", tab);
+                    }
+                }
+                if matches.is_present("midi") {
+                    write_midi(guitar_note::scale_midi(
+                        root_name, scale_name, &tuning, &window, &midi_options,
+                    ));
+                }
             } else {
                 println!("Sorry, I could not parse scale and/or root input.");
                 println!(
-                    "Scale should be one of: 
-                minor,
-                major,
-                minor_blues,
-                major_blues,
-                minor_pentatonic,
-                major_pentatonic,
-                dorian,
-                phrygian,
-                lydian,
-                mixolydian."
-                )
+                    "Scale should be one of: {}.",
+                    guitar_note::scale_names().join(", ")
+                );
             }
         }
         None => {}
     }
+    if let Some(mut vals) = matches.values_of("scale_formula") {
+        let relative = matches.is_present("relative");
+        let root_name = &vals.next().unwrap().to_lowercase()[..];
+        let formula = vals.next().unwrap();
+        if let Some(result) = guitar_note::scale_formula_on_fretboard(
+            formula, root_name, &tuning, relative, &window, &harmony,
+        ) {
+            println!("{}", result.2);
+            println!("{}", result.1);
+            println!();
+            let (fret_numbers, fret_markers) = fret_rows(&result.0);
+            println!("{}", fret_numbers);
+            println!();
+            println!("{}", result.0);
+            println!();
+            println!("{}", fret_markers);
+            if matches.is_present("tab") {
+                if let Some(tab) = guitar_note::scale_formula_tab(formula, root_name, &tuning, &window) {
+                    println!();
+                    println!("{}", tab);
+                }
+            }
+        } else {
+            println!("Sorry, I could not parse root and/or scale formula.");
+            println!("The formula should list degrees (\"1 2 b3 4 5 b6 7\") or steps (\"W H W W H A H\") of one octave.");
+        }
+    }
 }